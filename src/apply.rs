@@ -0,0 +1,616 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::config::{GpioConfig, PinConfig};
+use crate::error::*;
+use crate::export::{self, Operation, ReadyWait};
+use nix::unistd::{Gid, Uid};
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use sysfs_gpio::{Direction, Edge};
+
+/// Live state of an exported pin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinAttributes {
+    pub direction: Direction,
+    pub active_low: bool,
+    pub edge: Edge,
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+}
+
+/// State of a single entry in the symlink root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymlinkState {
+    Missing,
+    Target(PathBuf),
+    NotASymlink,
+}
+
+/// Live state of a pin and the symlinks for its names
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinState {
+    /// Attributes of the pin, or `None` if it is not exported
+    pub attributes: Option<PinAttributes>,
+    pub symlinks: Vec<(PathBuf, SymlinkState)>,
+}
+
+/// A single change required to bring a pin in line with its configuration
+///
+/// Values of `from` are `None` if the pin is not exported yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Export {
+        pin: u64,
+    },
+    Unexport {
+        pin: u64,
+        safe_value: Option<u8>,
+    },
+    SetOwner {
+        pin: u64,
+        from: Option<(u32, u32)>,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    },
+    SetMode {
+        pin: u64,
+        from: Option<u32>,
+        to: u32,
+    },
+    SetActiveLow {
+        pin: u64,
+        from: Option<bool>,
+        to: bool,
+    },
+    SetDirection {
+        pin: u64,
+        from: Option<Direction>,
+        to: Direction,
+    },
+    SetEdge {
+        pin: u64,
+        from: Option<Edge>,
+        to: Edge,
+    },
+    CreateSymlink {
+        path: PathBuf,
+        target: PathBuf,
+    },
+    ReplaceSymlink {
+        path: PathBuf,
+        from: PathBuf,
+        to: PathBuf,
+    },
+    RemoveSymlink {
+        path: PathBuf,
+        target: PathBuf,
+    },
+}
+
+/// Whether the live direction satisfies the configured one
+///
+/// sysfs only ever reports `in` or `out`; `high` and `low` just select the
+/// initial value of an output.
+fn direction_matches(live: Direction, configured: Direction) -> bool {
+    match configured {
+        Direction::In => live == Direction::In,
+        Direction::Out | Direction::High | Direction::Low => live != Direction::In,
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Change::Export { pin } => write!(f, "+ export gpio{}", pin),
+            Change::Unexport {
+                pin,
+                safe_value: None,
+            } => write!(f, "- unexport gpio{}", pin),
+            Change::Unexport {
+                pin,
+                safe_value: Some(value),
+            } => write!(f, "- unexport gpio{} (after setting value {})", pin, value),
+            Change::SetOwner {
+                pin,
+                from,
+                uid,
+                gid,
+            } => {
+                let owner = format!(
+                    "{}:{}",
+                    uid.map_or_else(|| String::from("-"), |u| u.to_string()),
+                    gid.map_or_else(|| String::from("-"), |g| g.to_string())
+                );
+                match from {
+                    Some((u, g)) => write!(f, "~ gpio{} owner: {}:{} -> {}", pin, u, g, owner),
+                    None => write!(f, "+ gpio{} owner = {}", pin, owner),
+                }
+            }
+            Change::SetMode { pin, from, to } => match from {
+                Some(from) => write!(f, "~ gpio{} mode: {:o} -> {:o}", pin, from, to),
+                None => write!(f, "+ gpio{} mode = {:o}", pin, to),
+            },
+            Change::SetActiveLow { pin, from, to } => match from {
+                Some(from) => write!(f, "~ gpio{} active_low: {} -> {}", pin, from, to),
+                None => write!(f, "+ gpio{} active_low = {}", pin, to),
+            },
+            Change::SetDirection { pin, from, to } => match from {
+                Some(from) => write!(
+                    f,
+                    "~ gpio{} direction: {} -> {}",
+                    pin,
+                    export::direction_str(from),
+                    export::direction_str(to)
+                ),
+                None => write!(f, "+ gpio{} direction = {}", pin, export::direction_str(to)),
+            },
+            Change::SetEdge { pin, from, to } => match from {
+                Some(from) => write!(
+                    f,
+                    "~ gpio{} edge: {} -> {}",
+                    pin,
+                    export::edge_str(from),
+                    export::edge_str(to)
+                ),
+                None => write!(f, "+ gpio{} edge = {}", pin, export::edge_str(to)),
+            },
+            Change::CreateSymlink {
+                ref path,
+                ref target,
+            } => write!(f, "+ symlink {} -> {}", path.display(), target.display()),
+            Change::ReplaceSymlink {
+                ref path,
+                ref from,
+                ref to,
+            } => write!(
+                f,
+                "~ symlink {}: {} -> {}",
+                path.display(),
+                from.display(),
+                to.display()
+            ),
+            Change::RemoveSymlink {
+                ref path,
+                ref target,
+            } => write!(f, "- symlink {} -> {}", path.display(), target.display()),
+        }
+    }
+}
+
+impl Change {
+    /// Describe the change in terms of the operations performing it
+    pub fn operations(&self, ready: &ReadyWait) -> Vec<Operation> {
+        match *self {
+            Change::Export { pin } => vec![
+                Operation::Export { pin },
+                Operation::WaitReady { pin, ready: *ready },
+            ],
+            Change::Unexport { pin, safe_value } => {
+                let mut operations = Vec::new();
+                if let Some(value) = safe_value {
+                    operations.push(Operation::SetValue { pin, value });
+                }
+                operations.push(Operation::Unexport { pin });
+                operations
+            }
+            Change::SetOwner { pin, uid, gid, .. } => vec![Operation::Chown {
+                pin,
+                uid: uid.map(Uid::from_raw),
+                gid: gid.map(Gid::from_raw),
+            }],
+            Change::SetMode { pin, to, .. } => vec![Operation::Chmod { pin, mode: to }],
+            Change::SetActiveLow { pin, to, .. } => vec![Operation::SetActiveLow {
+                pin,
+                active_low: to,
+            }],
+            Change::SetDirection { pin, to, .. } => {
+                vec![Operation::SetDirection { pin, direction: to }]
+            }
+            Change::SetEdge { pin, to, .. } => vec![Operation::SetEdge { pin, edge: to }],
+            Change::CreateSymlink {
+                ref path,
+                ref target,
+            } => {
+                let mut operations = Vec::new();
+                if let Some(parent) = path.parent() {
+                    operations.push(Operation::CreateDir {
+                        path: parent.to_path_buf(),
+                    });
+                }
+                operations.push(Operation::Symlink {
+                    target: target.clone(),
+                    path: path.clone(),
+                });
+                operations
+            }
+            Change::ReplaceSymlink {
+                ref path, ref to, ..
+            } => vec![Operation::Symlink {
+                target: to.clone(),
+                path: path.clone(),
+            }],
+            Change::RemoveSymlink { ref path, .. } => {
+                vec![Operation::RemoveSymlink { path: path.clone() }]
+            }
+        }
+    }
+
+    /// Perform the change
+    pub fn apply(&self, ready: &ReadyWait) -> Result<()> {
+        export::run(&self.operations(ready))
+    }
+}
+
+/// Observe the live state of a pin and the symlinks for its names
+pub fn observe(pin_config: &PinConfig, symlink_root: Option<&str>) -> Result<PinState> {
+    let pin = pin_config.get_pin();
+    let attributes = if pin.is_exported() {
+        let metadata = fs::metadata(export::sysfs_path(pin_config.num).join("value"))?;
+        Some(PinAttributes {
+            direction: pin.get_direction()?,
+            active_low: pin.get_active_low()?,
+            edge: pin.get_edge()?,
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            mode: metadata.mode() & 0o7777,
+        })
+    } else {
+        None
+    };
+
+    let mut symlinks = Vec::new();
+    if let Some(symroot) = symlink_root {
+        for name in &pin_config.names {
+            let mut path = PathBuf::from(symroot);
+            path.push(name);
+            let state = match fs::read_link(&path) {
+                Ok(target) => SymlinkState::Target(target),
+                Err(ref e) if e.kind() == ErrorKind::NotFound => SymlinkState::Missing,
+                Err(ref e) if e.kind() == ErrorKind::InvalidInput => SymlinkState::NotASymlink,
+                Err(e) => return Err(e.into()),
+            };
+            symlinks.push((path, state));
+        }
+    }
+
+    Ok(PinState {
+        attributes,
+        symlinks,
+    })
+}
+
+/// Compute the changes bringing a pin from `state` to its configuration
+///
+/// `owner` is the resolved uid/gid of the configured user/group.  Only the
+/// symlinks of pins with `export = false` are removed, as the pin may be
+/// exported by another process or a driver; it is unexported as well if
+/// `unexport_unmanaged` is set.  Returns an `Error::NotASymlink` if a name of
+/// the pin is taken by something other than a symlink in the symlink root.
+pub fn plan_pin(
+    pin_config: &PinConfig,
+    state: &PinState,
+    owner: (Option<u32>, Option<u32>),
+    unexport_unmanaged: bool,
+) -> Result<Vec<Change>> {
+    let num = pin_config.num;
+    let target = export::sysfs_path(num);
+    let mut changes = Vec::new();
+
+    if !pin_config.export {
+        for (path, link) in &state.symlinks {
+            if *link == SymlinkState::Target(target.clone()) {
+                changes.push(Change::RemoveSymlink {
+                    path: path.clone(),
+                    target: target.clone(),
+                });
+            }
+        }
+        match state.attributes.as_ref() {
+            Some(attrs) if unexport_unmanaged => {
+                let safe_value = match attrs.direction {
+                    Direction::Out => pin_config.safe_value,
+                    _ => None,
+                };
+                changes.push(Change::Unexport {
+                    pin: num,
+                    safe_value,
+                });
+            }
+            _ => (),
+        }
+        return Ok(changes);
+    }
+
+    let attrs = state.attributes.as_ref();
+    if attrs.is_none() {
+        changes.push(Change::Export { pin: num });
+    }
+
+    let (uid, gid) = owner;
+    let owner_differs = attrs.map_or(true, |a| {
+        uid.is_some_and(|u| u != a.uid) || gid.is_some_and(|g| g != a.gid)
+    });
+    if (uid.is_some() || gid.is_some()) && owner_differs {
+        changes.push(Change::SetOwner {
+            pin: num,
+            from: attrs.map(|a| (a.uid, a.gid)),
+            uid,
+            gid,
+        });
+    }
+
+    if let Some(mode) = pin_config.mode {
+        if attrs.map_or(true, |a| a.mode != mode) {
+            changes.push(Change::SetMode {
+                pin: num,
+                from: attrs.map(|a| a.mode),
+                to: mode,
+            });
+        }
+    }
+
+    if attrs.map_or(true, |a| a.active_low != pin_config.active_low) {
+        changes.push(Change::SetActiveLow {
+            pin: num,
+            from: attrs.map(|a| a.active_low),
+            to: pin_config.active_low,
+        });
+    }
+
+    if attrs.map_or(true, |a| {
+        !direction_matches(a.direction, pin_config.get_direction())
+    }) {
+        changes.push(Change::SetDirection {
+            pin: num,
+            from: attrs.map(|a| a.direction),
+            to: export::initial_direction(pin_config),
+        });
+    }
+
+    // set after the direction, as the kernel only allows edges on inputs
+    if let Some(edge) = pin_config.edge {
+        if attrs.map_or(true, |a| a.edge != edge) {
+            changes.push(Change::SetEdge {
+                pin: num,
+                from: attrs.map(|a| a.edge),
+                to: edge,
+            });
+        }
+    }
+
+    for (path, link) in &state.symlinks {
+        match *link {
+            SymlinkState::Missing => changes.push(Change::CreateSymlink {
+                path: path.clone(),
+                target: target.clone(),
+            }),
+            SymlinkState::Target(ref current) if *current != target => {
+                changes.push(Change::ReplaceSymlink {
+                    path: path.clone(),
+                    from: current.clone(),
+                    to: target.clone(),
+                })
+            }
+            SymlinkState::Target(_) => (),
+            SymlinkState::NotASymlink => {
+                return Err(Error::NotASymlink { path: path.clone() });
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Compute the changes bringing all configured pins in line with the config
+///
+/// Each configured pin is compared with what is currently found in sysfs
+/// and in the symlink root.  Applying the resulting changes in order brings
+/// the system in line with the configuration, after which a new plan is
+/// empty.  If `symlink_root` is `None`, symlinks are not taken into account.
+/// Pins with `export = false` are only unexported if `unexport_unmanaged` is
+/// set (see `plan_pin`).
+pub fn plan(
+    config: &GpioConfig,
+    symlink_root: Option<&str>,
+    unexport_unmanaged: bool,
+) -> Result<Vec<Change>> {
+    let mut changes = Vec::new();
+    for pin_config in config.get_pins() {
+        let state = observe(pin_config, symlink_root)?;
+        let (uid, gid) = if pin_config.export {
+            export::resolve_owner(pin_config)?
+        } else {
+            (None, None)
+        };
+        changes.extend(plan_pin(
+            pin_config,
+            &state,
+            (uid.map(Uid::as_raw), gid.map(Gid::as_raw)),
+            unexport_unmanaged,
+        )?);
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    const APPLY_CFG: &str = r#"
+[[pins]]
+num = 73
+names = ["reset_button"]
+active_low = true
+mode = 0o664
+edge = "both"
+
+[[pins]]
+num = 37
+names = ["status_led"]
+direction = "high"
+
+[[pins]]
+num = 40
+names = ["unused"]
+export = false
+safe_value = 0
+"#;
+
+    fn exported(direction: Direction, active_low: bool, mode: u32) -> Option<PinAttributes> {
+        Some(PinAttributes {
+            direction,
+            active_low,
+            edge: Edge::NoInterrupt,
+            uid: 0,
+            gid: 0,
+            mode,
+        })
+    }
+
+    #[test]
+    fn test_plan_unexported_pin() {
+        let config = GpioConfig::from_str(APPLY_CFG).unwrap();
+        let state = PinState {
+            attributes: None,
+            symlinks: vec![(
+                PathBuf::from("/run/gpio/reset_button"),
+                SymlinkState::Missing,
+            )],
+        };
+        let changes = plan_pin(&config.pins[0], &state, (None, None), false).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                Change::Export { pin: 73 },
+                Change::SetMode {
+                    pin: 73,
+                    from: None,
+                    to: 0o664
+                },
+                Change::SetActiveLow {
+                    pin: 73,
+                    from: None,
+                    to: true
+                },
+                Change::SetDirection {
+                    pin: 73,
+                    from: None,
+                    to: Direction::In
+                },
+                Change::SetEdge {
+                    pin: 73,
+                    from: None,
+                    to: Edge::BothEdges
+                },
+                Change::CreateSymlink {
+                    path: PathBuf::from("/run/gpio/reset_button"),
+                    target: PathBuf::from("/sys/class/gpio/gpio73"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_drifted_pin() {
+        let config = GpioConfig::from_str(APPLY_CFG).unwrap();
+        let state = PinState {
+            attributes: exported(Direction::In, false, 0o644),
+            symlinks: vec![(
+                PathBuf::from("/run/gpio/status_led"),
+                SymlinkState::Target(PathBuf::from("/sys/class/gpio/gpio38")),
+            )],
+        };
+        let changes = plan_pin(&config.pins[1], &state, (None, Some(20)), false).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                Change::SetOwner {
+                    pin: 37,
+                    from: Some((0, 0)),
+                    uid: None,
+                    gid: Some(20)
+                },
+                Change::SetDirection {
+                    pin: 37,
+                    from: Some(Direction::In),
+                    to: Direction::High
+                },
+                Change::ReplaceSymlink {
+                    path: PathBuf::from("/run/gpio/status_led"),
+                    from: PathBuf::from("/sys/class/gpio/gpio38"),
+                    to: PathBuf::from("/sys/class/gpio/gpio37"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_up_to_date_and_unwanted_pins() {
+        let config = GpioConfig::from_str(APPLY_CFG).unwrap();
+        let state = PinState {
+            attributes: exported(Direction::Out, false, 0o644),
+            symlinks: vec![(
+                PathBuf::from("/run/gpio/status_led"),
+                SymlinkState::Target(PathBuf::from("/sys/class/gpio/gpio37")),
+            )],
+        };
+        assert_eq!(
+            plan_pin(&config.pins[1], &state, (None, None), false).unwrap(),
+            vec![]
+        );
+
+        let state = PinState {
+            attributes: exported(Direction::In, false, 0o644),
+            symlinks: vec![(
+                PathBuf::from("/run/gpio/unused"),
+                SymlinkState::Target(PathBuf::from("/sys/class/gpio/gpio40")),
+            )],
+        };
+        // the pin may be exported by someone else, only its symlink is ours
+        assert_eq!(
+            plan_pin(&config.pins[2], &state, (None, None), false).unwrap(),
+            vec![Change::RemoveSymlink {
+                path: PathBuf::from("/run/gpio/unused"),
+                target: PathBuf::from("/sys/class/gpio/gpio40"),
+            }]
+        );
+        assert_eq!(
+            plan_pin(&config.pins[2], &state, (None, None), true).unwrap(),
+            vec![
+                Change::RemoveSymlink {
+                    path: PathBuf::from("/run/gpio/unused"),
+                    target: PathBuf::from("/sys/class/gpio/gpio40"),
+                },
+                Change::Unexport {
+                    pin: 40,
+                    safe_value: None,
+                },
+            ]
+        );
+
+        // outputs are driven to their safe value before being unexported
+        let state = PinState {
+            attributes: exported(Direction::Out, false, 0o644),
+            symlinks: vec![],
+        };
+        assert_eq!(
+            plan_pin(&config.pins[2], &state, (None, None), true).unwrap(),
+            vec![Change::Unexport {
+                pin: 40,
+                safe_value: Some(0),
+            }]
+        );
+    }
+}
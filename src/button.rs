@@ -0,0 +1,347 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::config::PinConfig;
+use crate::error::*;
+use crate::monitor::{self, Monitor};
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+/// Press duration from which a press is a long press, unless configured
+pub const DEFAULT_LONG_PRESS_MS: u64 = 1000;
+/// Time after a release within which a second press makes a double click,
+/// unless configured
+pub const DEFAULT_DOUBLE_CLICK_MS: u64 = 300;
+/// Debounce period of buttons without `debounce_ms` configured
+pub const DEFAULT_DEBOUNCE_MS: u64 = 20;
+
+/// A gesture performed on a button
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    ShortPress,
+    LongPress,
+    DoubleClick,
+}
+
+impl fmt::Display for Gesture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Gesture::ShortPress => write!(f, "short_press"),
+            Gesture::LongPress => write!(f, "long_press"),
+            Gesture::DoubleClick => write!(f, "double_click"),
+        }
+    }
+}
+
+/// Thresholds of a `GestureDetector`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GestureConfig {
+    /// Presses lasting this long are long presses (`None` to disable)
+    pub long_press: Option<Duration>,
+    /// A press starting within this time after the release of a short press
+    /// makes a double click (`None` to disable)
+    pub double_click: Option<Duration>,
+}
+
+impl GestureConfig {
+    /// Thresholds of a pin (`long_press_ms` and `double_click_ms`)
+    ///
+    /// The defaults are used for thresholds which are not configured, and a
+    /// threshold of 0 disables the gesture.
+    pub fn from_pin(pin_config: &PinConfig) -> GestureConfig {
+        let threshold = |ms: Option<u64>, default: u64| match ms.unwrap_or(default) {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        };
+        GestureConfig {
+            long_press: threshold(pin_config.long_press_ms, DEFAULT_LONG_PRESS_MS),
+            double_click: threshold(pin_config.double_click_ms, DEFAULT_DOUBLE_CLICK_MS),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// The button is released
+    Idle,
+    /// The button is pressed; `second` if it is the second press of a
+    /// double click
+    Pressed { since: Duration, second: bool },
+    /// The button was released after a short press, which may be the first
+    /// press of a double click
+    Released { at: Duration },
+    /// The button is still pressed after a gesture was reported
+    Held,
+}
+
+/// Turns the presses and releases of a button into gestures
+///
+/// This is a pure state machine like `EdgeFilter`: the caller feeds it the
+/// (debounced) edges with `edge` and calls `poll` once `deadline` has
+/// passed.  A long press is reported as soon as the button has been held
+/// for the threshold, a double click when the second press is released, and
+/// a short press once no second press followed within the double click
+/// threshold (right on release if double clicks are disabled).
+#[derive(Debug, Clone)]
+pub struct GestureDetector {
+    config: GestureConfig,
+    state: State,
+}
+
+impl GestureDetector {
+    /// Create a detector for a button which is currently `pressed` or not
+    ///
+    /// A press in progress does not count as a gesture.
+    pub fn new(config: GestureConfig, pressed: bool) -> GestureDetector {
+        GestureDetector {
+            config,
+            state: if pressed { State::Held } else { State::Idle },
+        }
+    }
+
+    /// Feed an edge after which the button is `pressed` or not at time `at`
+    pub fn edge(&mut self, pressed: bool, at: Duration) -> Option<Gesture> {
+        let (state, gesture) = match (self.state, pressed) {
+            (State::Idle, true) => (
+                State::Pressed {
+                    since: at,
+                    second: false,
+                },
+                None,
+            ),
+            (State::Released { .. }, true) => (
+                State::Pressed {
+                    since: at,
+                    second: true,
+                },
+                None,
+            ),
+            (State::Pressed { second: true, .. }, false) => {
+                (State::Idle, Some(Gesture::DoubleClick))
+            }
+            (State::Pressed { second: false, .. }, false) => match self.config.double_click {
+                Some(_) => (State::Released { at }, None),
+                None => (State::Idle, Some(Gesture::ShortPress)),
+            },
+            (State::Held, false) => (State::Idle, None),
+            // no change of level
+            (state, _) => (state, None),
+        };
+        self.state = state;
+        gesture
+    }
+
+    /// Time at which a gesture is reported unless another edge comes first
+    pub fn deadline(&self) -> Option<Duration> {
+        match self.state {
+            State::Pressed {
+                since,
+                second: false,
+            } => self.config.long_press.map(|long_press| since + long_press),
+            State::Released { at } => self
+                .config
+                .double_click
+                .map(|double_click| at + double_click),
+            _ => None,
+        }
+    }
+
+    /// Report the gesture whose deadline has passed at time `now`, if any
+    pub fn poll(&mut self, now: Duration) -> Option<Gesture> {
+        if self.deadline()? > now {
+            return None;
+        }
+        match self.state {
+            State::Pressed { .. } => {
+                self.state = State::Held;
+                Some(Gesture::LongPress)
+            }
+            State::Released { .. } => {
+                self.state = State::Idle;
+                Some(Gesture::ShortPress)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A gesture detected on a button
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GestureEvent {
+    pub pin: u64,
+    /// The name the button was created with
+    pub name: String,
+    pub gesture: Gesture,
+    /// Time since boot (`CLOCK_MONOTONIC`) at which the gesture completed
+    pub monotonic: Duration,
+    /// Wall clock time at which the gesture completed
+    pub realtime: SystemTime,
+}
+
+/// Detect gestures on a button connected to an exported pin
+///
+/// The button is pressed while the pin reads 1 (so `active_low` must be set
+/// for buttons pulling the line low).  Edges are debounced as configured by
+/// `debounce_ms` (default `DEFAULT_DEBOUNCE_MS`) and `min_pulse_ms`, and the
+/// thresholds are taken from `long_press_ms` and `double_click_ms` (see
+/// `GestureConfig::from_pin`).
+pub struct Button {
+    monitor: Monitor,
+    detector: GestureDetector,
+    num: u64,
+    name: String,
+}
+
+impl Button {
+    pub fn new(pin_config: &PinConfig, name: &str) -> Result<Button> {
+        let pin_config = PinConfig {
+            debounce_ms: pin_config.debounce_ms.or(Some(DEFAULT_DEBOUNCE_MS)),
+            ..pin_config.clone()
+        };
+        let mut monitor = Monitor::new()?;
+        monitor.add(&pin_config, name)?;
+        let pressed = pin_config
+            .get_pin()
+            .get_value()
+            .map_err(|e| Error::pin_operation(pin_config.num, "read value", e))?
+            == 1;
+        Ok(Button {
+            monitor,
+            detector: GestureDetector::new(GestureConfig::from_pin(&pin_config), pressed),
+            num: pin_config.num,
+            name: name.to_string(),
+        })
+    }
+
+    /// Wait for the next gesture
+    ///
+    /// Returns `None` if no gesture was completed within `timeout` (if any).
+    pub fn next_gesture(&mut self, timeout: Option<Duration>) -> Result<Option<GestureEvent>> {
+        let deadline = match timeout {
+            Some(timeout) => Some(monitor::now()?.0 + timeout),
+            None => None,
+        };
+        loop {
+            let (monotonic, realtime) = monitor::now()?;
+            if let Some(at) = self.detector.deadline() {
+                if let Some(gesture) = self.detector.poll(monotonic) {
+                    let realtime = realtime - monotonic.saturating_sub(at);
+                    return Ok(Some(self.event(gesture, at, realtime)));
+                }
+            }
+            if deadline.is_some_and(|deadline| monotonic >= deadline) {
+                return Ok(None);
+            }
+
+            let wake = self.detector.deadline().into_iter().chain(deadline).min();
+            let event = self
+                .monitor
+                .next_event(wake.map(|wake| wake.saturating_sub(monotonic)))?;
+            if let Some(event) = event {
+                if let Some(gesture) = self.detector.edge(event.value == 1, event.monotonic) {
+                    return Ok(Some(self.event(gesture, event.monotonic, event.realtime)));
+                }
+            }
+        }
+    }
+
+    fn event(&self, gesture: Gesture, monotonic: Duration, realtime: SystemTime) -> GestureEvent {
+        GestureEvent {
+            pin: self.num,
+            name: self.name.clone(),
+            gesture,
+            monotonic,
+            realtime,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn detector(long_press: Option<u64>, double_click: Option<u64>) -> GestureDetector {
+        let config = GestureConfig {
+            long_press: long_press.map(ms),
+            double_click: double_click.map(ms),
+        };
+        GestureDetector::new(config, false)
+    }
+
+    #[test]
+    fn test_short_press() {
+        let mut d = detector(Some(1000), Some(300));
+        assert_eq!(d.edge(true, ms(100)), None);
+        assert_eq!(d.deadline(), Some(ms(1100)));
+        assert_eq!(d.edge(false, ms(200)), None);
+        // reported once no second press followed
+        assert_eq!(d.deadline(), Some(ms(500)));
+        assert_eq!(d.poll(ms(499)), None);
+        assert_eq!(d.poll(ms(500)), Some(Gesture::ShortPress));
+        assert_eq!(d.deadline(), None);
+
+        // right on release without double clicks
+        let mut d = detector(Some(1000), None);
+        assert_eq!(d.edge(true, ms(100)), None);
+        assert_eq!(d.edge(false, ms(200)), Some(Gesture::ShortPress));
+    }
+
+    #[test]
+    fn test_long_press() {
+        let mut d = detector(Some(1000), Some(300));
+        assert_eq!(d.edge(true, ms(100)), None);
+        assert_eq!(d.poll(ms(1099)), None);
+        // reported while the button is still held, and only once
+        assert_eq!(d.poll(ms(1100)), Some(Gesture::LongPress));
+        assert_eq!(d.deadline(), None);
+        assert_eq!(d.edge(false, ms(3000)), None);
+        assert_eq!(d.poll(ms(5000)), None);
+
+        // without long presses, any press is a short press
+        let mut d = detector(None, None);
+        assert_eq!(d.edge(true, ms(100)), None);
+        assert_eq!(d.deadline(), None);
+        assert_eq!(d.edge(false, ms(5000)), Some(Gesture::ShortPress));
+    }
+
+    #[test]
+    fn test_double_click() {
+        let mut d = detector(Some(1000), Some(300));
+        assert_eq!(d.edge(true, ms(100)), None);
+        assert_eq!(d.edge(false, ms(200)), None);
+        assert_eq!(d.edge(true, ms(400)), None);
+        // the second press is not timed
+        assert_eq!(d.deadline(), None);
+        assert_eq!(d.edge(false, ms(2000)), Some(Gesture::DoubleClick));
+
+        // a second press after the threshold is a new press
+        assert_eq!(d.edge(true, ms(3000)), None);
+        assert_eq!(d.edge(false, ms(3100)), None);
+        assert_eq!(d.poll(ms(3400)), Some(Gesture::ShortPress));
+        assert_eq!(d.edge(true, ms(3500)), None);
+        assert_eq!(d.edge(false, ms(3600)), None);
+        assert_eq!(d.poll(ms(3900)), Some(Gesture::ShortPress));
+    }
+
+    #[test]
+    fn test_initially_pressed() {
+        let config = GestureConfig {
+            long_press: Some(ms(1000)),
+            double_click: None,
+        };
+        let mut d = GestureDetector::new(config, true);
+        assert_eq!(d.deadline(), None);
+        assert_eq!(d.edge(false, ms(100)), None);
+        assert_eq!(d.edge(true, ms(200)), None);
+        assert_eq!(d.edge(false, ms(300)), Some(Gesture::ShortPress));
+    }
+}
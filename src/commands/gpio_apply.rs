@@ -0,0 +1,45 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::apply;
+use crate::error::*;
+use crate::gpio::Gpio;
+use crate::options::GpioApplyOptions;
+
+pub fn main(gpio: &Gpio, opts: &GpioApplyOptions) -> Result<()> {
+    let config = gpio.config();
+    let symlink_root = match (opts.no_symlinks, opts.symlink_root) {
+        (true, _) => None,
+        (false, Some(slr)) => Some(slr),
+        (false, None) => Some(config.get_symlink_root()),
+    };
+
+    let changes = apply::plan(config, symlink_root, opts.unexport_unmanaged)
+        .map_err(|e| Error::context("Error computing plan", e))?;
+
+    if changes.is_empty() {
+        println!("No changes, GPIOs match the configuration");
+        return Ok(());
+    }
+
+    if opts.plan {
+        for change in &changes {
+            println!("{}", change);
+        }
+        return Err(Error::ChangesPending);
+    }
+
+    let ready = config.get_ready_wait();
+    for change in &changes {
+        println!("{}", change);
+        change
+            .apply(&ready)
+            .map_err(|e| Error::context("Error applying change", e))?;
+    }
+    Ok(())
+}
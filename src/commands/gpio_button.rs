@@ -0,0 +1,88 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::button::{Gesture, GestureEvent};
+use crate::commands;
+use crate::config::PinConfig;
+use crate::error::*;
+use crate::gpio::{AccessOptions, Gpio};
+use crate::options::{GpioButtonOptions, OutputFormat};
+use serde_json::{json, Map, Value};
+use std::process::Command;
+
+pub fn main(gpio: &Gpio, opts: &GpioButtonOptions) -> Result<()> {
+    let format = opts.gpio_opts.format;
+    let pin_config = commands::resolve_pin(gpio, opts.pin, format)?;
+    let pin_config = PinConfig {
+        long_press_ms: opts.long_press_ms.or(pin_config.long_press_ms),
+        double_click_ms: opts.double_click_ms.or(pin_config.double_click_ms),
+        ..commands::with_filter(&pin_config, opts.debounce_ms, None)
+    };
+    let access = AccessOptions {
+        keep: opts.keep,
+        ..Default::default()
+    };
+
+    let mut seen = 0;
+    gpio.button(&pin_config, opts.pin, &access, |event| {
+        let command = match event.gesture {
+            Gesture::ShortPress => &pin_config.on_short_press,
+            Gesture::LongPress => &pin_config.on_long_press,
+            Gesture::DoubleClick => &pin_config.on_double_click,
+        };
+        match command {
+            Some(command) if !opts.no_exec => run_command(command, event, format),
+            _ => print_gesture(event, format),
+        }
+        seen += 1;
+        Ok(opts.count.map_or(true, |count| seen < count))
+    })
+}
+
+/// Run the command configured for a gesture with `sh -c`
+///
+/// The command is waited for, so gestures performed in the meantime are
+/// only reported once it has finished.  Failures are reported as warnings.
+fn run_command(command: &str, event: &GestureEvent, format: OutputFormat) {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("GPIO_PIN", event.pin.to_string())
+        .env("GPIO_NAME", &event.name)
+        .env("GPIO_GESTURE", event.gesture.to_string())
+        .status();
+    let msg = match status {
+        Ok(status) if status.success() => return,
+        Ok(status) => format!(
+            "{} command '{}' failed ({})",
+            event.gesture, command, status
+        ),
+        Err(e) => format!(
+            "unable to run {} command '{}': {}",
+            event.gesture, command, e
+        ),
+    };
+    match format {
+        OutputFormat::Text => eprintln!("Warning: {}", msg),
+        OutputFormat::Json => eprintln!("{}", json!({ "warning": msg })),
+    }
+}
+
+fn print_gesture(event: &GestureEvent, format: OutputFormat) {
+    match format {
+        OutputFormat::Text => println!("{}", event.gesture),
+        OutputFormat::Json => {
+            let mut object = Map::new();
+            object.insert(String::from("pin"), json!(event.pin));
+            object.insert(String::from("name"), json!(event.name));
+            object.insert(String::from("gesture"), json!(event.gesture.to_string()));
+            commands::timestamps_json(&mut object, event.monotonic, event.realtime);
+            println!("{}", Value::Object(object));
+        }
+    }
+}
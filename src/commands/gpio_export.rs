@@ -6,28 +6,28 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::config::GpioConfig;
-use crate::export;
+use crate::commands;
+use crate::error::*;
+use crate::export::ExportOptions;
+use crate::gpio::Gpio;
 use crate::options::GpioExportOptions;
-use std::process::exit;
 
-pub fn main(config: &GpioConfig, opts: &GpioExportOptions) {
-    let pin = match config.get_pin(opts.pin) {
-        Some(pin) => pin,
-        None => {
-            println!("Unable to find config entry for pin '{}'", opts.pin);
-            exit(1)
-        }
-    };
+pub fn main(gpio: &Gpio, opts: &GpioExportOptions) -> Result<()> {
+    let pin = commands::resolve_pin(gpio, opts.pin, opts.gpio_opts.format)?;
 
-    let symlink_root = match opts.symlink_root {
-        Some(slr) => slr,
-        None => config.get_symlink_root(),
+    let defaults = gpio.export_options();
+    let export_opts = ExportOptions {
+        symlink_root: match (opts.no_symlinks, opts.symlink_root) {
+            (true, _) => None,
+            (false, Some(slr)) => Some(slr),
+            (false, None) => defaults.symlink_root,
+        },
+        ..defaults
     };
 
-    if let Err(e) = export::export(pin, Some(symlink_root)) {
-        println!("Error occurred while exporting pin: {:?}", pin);
-        println!("{}", e);
-        exit(1);
+    if opts.dry_run {
+        commands::print_operations(&gpio.export_operations(&pin, &export_opts)?);
+        return Ok(());
     }
+    gpio.export(&pin, &export_opts)
 }
@@ -6,23 +6,92 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::config::GpioConfig;
-use crate::export;
+use crate::commands;
+use crate::config::PinConfig;
+use crate::error::*;
+use crate::export::{self, ExportOptions, Operation, Transaction};
+use crate::gpio::Gpio;
 use crate::options::GpioExportAllOptions;
-use std::process::exit;
+use std::time::Duration;
 
-pub fn main(config: &GpioConfig, opts: &GpioExportAllOptions) {
-    let symlink_root = match opts.symlink_root {
-        Some(slr) => slr,
-        None => config.get_symlink_root(),
+pub fn main(gpio: &Gpio, opts: &GpioExportAllOptions) -> Result<()> {
+    let config = gpio.config();
+    let symlink_root = match (opts.no_symlinks, opts.symlink_root) {
+        (true, _) => None,
+        (false, Some(slr)) => Some(slr),
+        (false, None) => Some(config.get_symlink_root()),
     };
+    let export_opts = ExportOptions {
+        symlink_root,
+        ready: config.get_ready_wait(),
+    };
+
+    let pins = config
+        .export_order()
+        .map_err(|e| Error::context("Error determining export order", e))?;
+
+    let mut transaction = Transaction::new();
+    let mut results: Vec<(&PinConfig, Result<()>)> = Vec::new();
+
+    // export all pins except those for which export is set to false, each
+    // after the pins it depends on (and its delay)
+    for pin in pins.into_iter().filter(|p| p.export) {
+        let operations = export::export_operations(pin, &export_opts).map(|operations| {
+            let delay = pin.delay_ms.map(|ms| Operation::Delay {
+                duration: Duration::from_millis(ms),
+            });
+            delay.into_iter().chain(operations).collect::<Vec<_>>()
+        });
+        let result = match operations {
+            Ok(operations) if opts.dry_run => {
+                commands::print_operations(&operations);
+                Ok(())
+            }
+            Ok(operations) => transaction.run(&operations),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            if opts.keep_going {
+                results.push((pin, Err(e)));
+                continue;
+            }
+
+            if opts.atomic {
+                match transaction.rollback() {
+                    Ok(()) => eprintln!("Rolled back previously exported pins"),
+                    Err(e) => eprintln!("Error occurred during rollback: {}", e),
+                }
+            }
+            return Err(e);
+        }
+        results.push((pin, result));
+    }
 
-    // export all pins except those for which export is set to false
-    for pin in config.get_pins().iter().filter(|p| p.export) {
-        if let Err(e) = export::export(pin, Some(symlink_root)) {
-            println!("Error occurred while exporting pin: {:?}", pin);
-            println!("{}", e);
-            exit(1);
+    if opts.keep_going {
+        print_summary(&results);
+        // fail with the first failure
+        if let Some(pos) = results.iter().position(|(_, r)| r.is_err()) {
+            return results.swap_remove(pos).1;
         }
     }
+    Ok(())
+}
+
+fn print_summary(results: &[(&PinConfig, Result<()>)]) {
+    println!("| {:<10} | {:<20} | {:<10}", "Number", "Names", "Result");
+    println!("+{:-<12}+{:-<22}+{:-<10}", "", "", "");
+    for (pin, result) in results {
+        let names: Vec<&str> = pin.names.iter().map(|n| &n[..]).collect();
+        let result = match *result {
+            Ok(()) => String::from("ok"),
+            Err(ref e) => format!("FAILED: {}", e),
+        };
+        println!("| {:<10} | {:<20} | {}", pin.num, names.join(","), result);
+    }
+    let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+    println!(
+        "{} of {} pins exported successfully",
+        results.len() - failed,
+        results.len()
+    );
 }
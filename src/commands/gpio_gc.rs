@@ -0,0 +1,57 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::error::*;
+use crate::export::{self, StaleReason};
+use crate::gpio::Gpio;
+use crate::options::GpioGcOptions;
+use std::fs;
+
+pub fn main(gpio: &Gpio, opts: &GpioGcOptions) -> Result<()> {
+    let config = gpio.config();
+    let symlink_root = match opts.symlink_root {
+        Some(slr) => slr,
+        None => config.get_symlink_root(),
+    };
+
+    let stale = export::find_stale_symlinks(config, symlink_root).map_err(|e| {
+        Error::context(
+            format_args!("Error scanning symlink root {:?}", symlink_root),
+            e,
+        )
+    })?;
+
+    for link in stale {
+        let reason = match link.reason {
+            StaleReason::Orphaned => "orphaned",
+            StaleReason::Dangling => "dangling",
+        };
+        if opts.dry_run {
+            println!(
+                "would remove {} symlink {} -> {}",
+                reason,
+                link.path.display(),
+                link.target.display()
+            );
+            continue;
+        }
+        fs::remove_file(&link.path).map_err(|e| {
+            Error::context(
+                format_args!("Error removing symlink {}", link.path.display()),
+                e,
+            )
+        })?;
+        println!(
+            "removed {} symlink {} -> {}",
+            reason,
+            link.path.display(),
+            link.target.display()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::commands;
+use crate::config::PinConfig;
+use crate::error::*;
+use crate::export;
+use crate::gpio::Gpio;
+use crate::monitor::{Event, Monitor};
+use crate::options::{GpioMonitorOptions, OutputFormat};
+use serde_json::{json, Map, Value};
+use sysfs_gpio::Direction;
+
+pub fn main(gpio: &Gpio, opts: &GpioMonitorOptions) -> Result<()> {
+    let config = gpio.config();
+    let format = opts.gpio_opts.format;
+    let mut monitor = Monitor::new().map_err(|e| Error::context("Error creating monitor", e))?;
+
+    let mut add = |pin_config: &PinConfig, name: &str| {
+        let pin_config = commands::with_filter(pin_config, opts.debounce_ms, opts.min_pulse_ms);
+        monitor
+            .add(&pin_config, name)
+            .map_err(|e| Error::context(format_args!("Error monitoring pin '{}'", name), e))
+    };
+    if opts.pins.is_empty() {
+        // watch all configured inputs
+        for pin_config in config.get_pins() {
+            if pin_config.get_direction() == Direction::In {
+                let name = match pin_config.names.iter().next() {
+                    Some(name) => name.clone(),
+                    None => pin_config.num.to_string(),
+                };
+                add(pin_config, &name)?;
+            }
+        }
+    } else {
+        for name in &opts.pins {
+            let pin_config = commands::resolve_pin(gpio, name, format)?;
+            add(&pin_config, name)?;
+        }
+    }
+
+    let mut seen = 0;
+    while opts.count.map_or(true, |count| seen < count) {
+        match monitor.next_event(None) {
+            Ok(Some(event)) => print_event(&event, format),
+            Ok(None) => continue,
+            // running until interrupted is the normal way to stop
+            Err(Error::Interrupted { .. }) => break,
+            Err(e) => return Err(Error::context("Error waiting for events", e)),
+        }
+        seen += 1;
+    }
+    Ok(())
+}
+
+fn print_event(event: &Event, format: OutputFormat) {
+    match format {
+        OutputFormat::Text => println!("{}", event),
+        OutputFormat::Json => {
+            let mut object = Map::new();
+            object.insert(String::from("pin"), json!(event.pin));
+            object.insert(String::from("name"), json!(event.name));
+            object.insert(String::from("value"), json!(event.value));
+            object.insert(String::from("edge"), json!(export::edge_str(event.edge)));
+            commands::timestamps_json(&mut object, event.monotonic, event.realtime);
+            println!("{}", Value::Object(object));
+        }
+    }
+}
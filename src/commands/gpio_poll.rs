@@ -6,53 +6,34 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::config::GpioConfig;
+use crate::commands;
+use crate::error::*;
+use crate::gpio::{AccessOptions, Gpio};
 use crate::options::GpioPollOptions;
-use std::process::exit;
 use sysfs_gpio::Edge;
 
-pub fn main(config: &GpioConfig, opts: &GpioPollOptions) {
-    let timeout = opts.timeout.unwrap_or(-1);
-    let pin_config = match config.get_pin(opts.pin) {
-        Some(pin) => pin,
-        None => {
-            println!("Unable to find config entry for pin '{}'", opts.pin);
-            exit(1)
-        }
-    };
-    let pin = pin_config.get_pin();
+pub fn main(gpio: &Gpio, opts: &GpioPollOptions) -> Result<()> {
+    let format = opts.gpio_opts.format;
+    let pin_config = commands::resolve_pin(gpio, opts.pin, format)?;
+    let pin_config = commands::with_filter(&pin_config, opts.debounce_ms, opts.min_pulse_ms);
     let edge = match opts.edge {
         "rising" => Edge::RisingEdge,
         "falling" => Edge::FallingEdge,
         "both" => Edge::BothEdges,
-        other => {
-            println!("Unexpected edge value: {}", other);
-            exit(1);
-        }
+        other => return Err(Error::Msg(format!("Unexpected edge value: {}", other))),
+    };
+    let timeout = commands::timeout_from_ms(opts.timeout);
+    let access = AccessOptions {
+        keep: opts.keep,
+        ..Default::default()
     };
 
-    // set the pin direction
-    pin.set_edge(edge).unwrap_or_else(|e| {
-        println!("Error setting edge on pin: {:?}", e);
-        exit(1);
-    });
-
-    let mut poller = pin.get_poller().unwrap_or_else(|e| {
-        println!("Error creating pin poller: {:?}", e);
-        exit(1);
-    });
-    match poller.poll(timeout) {
-        Ok(Some(value)) => {
-            println!("{}", value);
-            exit(0);
-        }
-        Ok(None) => {
-            println!("TIMEOUT");
-            exit(2)
-        }
-        Err(e) => {
-            println!("Error on Poll: {:?}", e);
-            exit(1);
-        }
+    let value = gpio.poll(&pin_config, edge, timeout, &access)?;
+    commands::print_wait_result(&pin_config, value, format);
+    match value {
+        Some(_) => Ok(()),
+        None => Err(Error::PollTimeout {
+            pin: pin_config.num,
+        }),
     }
 }
@@ -6,22 +6,26 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::config::GpioConfig;
-use crate::options::GpioReadOptions;
-use std::process::exit;
+use crate::commands;
+use crate::error::*;
+use crate::gpio::{AccessOptions, Gpio};
+use crate::options::{GpioReadOptions, OutputFormat};
+use serde_json::{json, Value};
 
-pub fn main(config: &GpioConfig, opts: &GpioReadOptions) {
-    let pin_config = match config.get_pin(opts.pin) {
-        Some(pin) => pin,
-        None => {
-            println!("Unable to find config entry for pin '{}'", opts.pin);
-            exit(1)
-        }
+pub fn main(gpio: &Gpio, opts: &GpioReadOptions) -> Result<()> {
+    let pin_config = commands::resolve_pin(gpio, opts.pin, opts.gpio_opts.format)?;
+    let access = AccessOptions {
+        keep: opts.keep,
+        ..Default::default()
     };
-
-    let pin = pin_config.get_pin();
-    match pin.get_value() {
-        Ok(value) => println!("{}", value),
-        Err(e) => println!("ERROR: {:?}", e),
+    let value = gpio.read(&pin_config, &access)?;
+    match opts.gpio_opts.format {
+        OutputFormat::Text => println!("{}", value),
+        OutputFormat::Json => {
+            let mut object = commands::pin_json(&pin_config);
+            object.insert(String::from("value"), json!(value));
+            println!("{}", Value::Object(object));
+        }
     }
+    Ok(())
 }
@@ -0,0 +1,36 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::commands;
+use crate::error::*;
+use crate::export;
+use crate::gpio::Gpio;
+use crate::options::GpioSafeStateOptions;
+
+pub fn main(gpio: &Gpio, opts: &GpioSafeStateOptions) -> Result<()> {
+    let mut errors = Vec::new();
+    // keep going on errors so that as many outputs as possible end up safe
+    for pin in gpio.config().get_pins() {
+        let operations = export::safe_state_operations(pin);
+        if opts.dry_run {
+            commands::print_operations(&operations);
+        } else if let Err(e) = export::run(&operations) {
+            errors.push(Error::context("Error setting safe value", e));
+        }
+    }
+
+    // report all but the last error here, the last one is returned
+    let last = errors.pop();
+    for e in errors {
+        eprintln!("{}", e);
+    }
+    match last {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
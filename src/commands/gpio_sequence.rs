@@ -0,0 +1,28 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::error::*;
+use crate::gpio::Gpio;
+use crate::options::GpioSequenceOptions;
+use crate::sequence;
+
+pub fn main(gpio: &Gpio, opts: &GpioSequenceOptions) -> Result<()> {
+    let config = gpio.config();
+    let seq = config
+        .get_sequence(opts.name)
+        .ok_or_else(|| Error::UnknownSequence {
+            name: opts.name.to_string(),
+        })?;
+
+    for step in &seq.steps {
+        println!("{}", step);
+        sequence::run_step(config, step)
+            .map_err(|e| Error::context(format_args!("Error running step '{}'", step), e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::commands;
+use crate::config::GpioConfig;
+use crate::error::*;
+use crate::export;
+use crate::gpio::Gpio;
+use crate::options::{GpioStateAction, GpioStateOptions};
+use crate::state::Snapshot;
+use std::fs;
+use std::str::FromStr;
+
+pub fn main(gpio: &Gpio, opts: &GpioStateOptions) -> Result<()> {
+    let config = gpio.config();
+    match opts.action {
+        GpioStateAction::Save => save(config, opts.file),
+        GpioStateAction::Restore { dry_run } => restore(config, opts.file, dry_run),
+        GpioStateAction::Diff => diff(config, opts.file),
+    }
+}
+
+fn capture(config: &GpioConfig) -> Result<Snapshot> {
+    Snapshot::capture(config).map_err(|e| Error::context("Error capturing GPIO state", e))
+}
+
+fn load(file: &str) -> Result<Snapshot> {
+    let contents = fs::read_to_string(file)
+        .map_err(|e| Error::context(format_args!("Error reading snapshot {:?}", file), e))?;
+    Snapshot::from_str(&contents)
+        .map_err(|e| Error::context(format_args!("Error parsing snapshot {:?}", file), e))
+}
+
+fn save(config: &GpioConfig, file: &str) -> Result<()> {
+    let snapshot = capture(config)?;
+    fs::write(file, snapshot.to_string())
+        .map_err(|e| Error::context(format_args!("Error writing snapshot {:?}", file), e))?;
+    println!("Saved state of {} pins to {}", snapshot.pins.len(), file);
+    Ok(())
+}
+
+fn restore(config: &GpioConfig, file: &str, dry_run: bool) -> Result<()> {
+    let snapshot = load(file)?;
+    let mismatches = snapshot.validate(config);
+    if !mismatches.is_empty() {
+        return Err(Error::context(
+            format_args!("Error restoring snapshot {:?}", file),
+            Error::SnapshotMismatch { mismatches },
+        ));
+    }
+
+    let operations = snapshot.restore_operations(&config.get_ready_wait());
+    if dry_run {
+        commands::print_operations(&operations);
+        return Ok(());
+    }
+    export::run(&operations)
+        .map_err(|e| Error::context(format_args!("Error restoring snapshot {:?}", file), e))
+}
+
+fn diff(config: &GpioConfig, file: &str) -> Result<()> {
+    let snapshot = load(file)?;
+    let differences = snapshot.diff(&capture(config)?);
+    if differences.is_empty() {
+        println!("No differences, GPIOs match the snapshot");
+        return Ok(());
+    }
+    for difference in differences {
+        println!("{}", difference);
+    }
+    Err(Error::ChangesPending)
+}
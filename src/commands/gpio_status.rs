@@ -6,38 +6,58 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::config::GpioConfig;
-use crate::config::PinConfig;
-use crate::options::GpioStatusOptions;
-use std::process::exit;
+use crate::commands;
+use crate::error::*;
+use crate::export;
+use crate::gpio::{Gpio, PinStatus};
+use crate::options::{GpioStatusOptions, OutputFormat};
+use serde_json::{json, Map, Value};
 use sysfs_gpio::Direction;
 
-pub fn main(config: &GpioConfig, opts: &GpioStatusOptions) {
-    match opts.pin {
-        Some(ref pin_name) => {
-            let pin_config = match config.get_pin(pin_name) {
-                Some(pin) => pin,
-                None => {
-                    println!("Unable to find config entry for pin '{}'", pin_name);
-                    exit(1)
-                }
-            };
-            print_pin_header();
-            print_pin_row(pin_config, true);
-        }
-        None => {
-            print_pin_header();
-            for (pos, pin) in config.get_pins().iter().enumerate() {
-                print_pin_row(pin, pos == (config.get_pins().len() - 1));
-            }
+pub fn main(gpio: &Gpio, opts: &GpioStatusOptions) -> Result<()> {
+    let statuses = match opts.pin {
+        Some(pin_name) => {
+            let pin_config = commands::resolve_pin(gpio, pin_name, opts.gpio_opts.format)?;
+            vec![gpio.status(&pin_config)?]
         }
+        None => gpio.statuses()?,
+    };
+
+    if opts.gpio_opts.format == OutputFormat::Json {
+        statuses.iter().for_each(print_pin_json);
+        return Ok(());
     }
+
+    print_pin_header();
+    for (pos, status) in statuses.iter().enumerate() {
+        print_pin_row(status, pos == statuses.len() - 1);
+    }
+    Ok(())
+}
+
+fn print_pin_json(status: &PinStatus) {
+    let mut object = Map::new();
+    object.insert(String::from("pin"), json!(status.num));
+    object.insert(String::from("names"), json!(status.names));
+    object.insert(String::from("export"), json!(status.export));
+    object.insert(String::from("exported"), json!(status.exported));
+    object.insert(
+        String::from("direction"),
+        json!(export::direction_str(status.direction)),
+    );
+    object.insert(String::from("active_low"), json!(status.active_low));
+    object.insert(
+        String::from("edge"),
+        json!(status.edge.map(export::edge_str)),
+    );
+    object.insert(String::from("value"), json!(status.value));
+    println!("{}", Value::Object(object));
 }
 
 fn print_pin_header() {
     println!(
-        "| {:<10} | {:<10} | {:<10} | {:<10} | {:<10} | {:<10} |",
-        "Number", "Exported", "Direction", "Active Low", "Names", "Value"
+        "| {:<10} | {:<10} | {:<10} | {:<10} | {:<10} | {:<10} | {:<10} |",
+        "Number", "Exported", "Direction", "Active Low", "Edge", "Names", "Value"
     );
     print_row_sep(false);
 }
@@ -45,37 +65,39 @@ fn print_pin_header() {
 fn print_row_sep(is_last: bool) {
     let col_sep = if is_last { "-" } else { "+" };
     println!(
-        "{}{:->13}{:->13}{:->13}{:->13}{:->13}{:->13}",
-        col_sep, col_sep, col_sep, col_sep, col_sep, col_sep, col_sep
+        "{}{:->13}{:->13}{:->13}{:->13}{:->13}{:->13}{:->13}",
+        col_sep, col_sep, col_sep, col_sep, col_sep, col_sep, col_sep, col_sep
     );
 }
 
-fn print_pin_row(pin_config: &PinConfig, is_last: bool) {
-    let direction = match pin_config.direction {
+fn print_pin_row(status: &PinStatus, is_last: bool) {
+    let direction = match status.direction {
         Direction::In => "In",
         Direction::Out => "Out",
         Direction::High => "High",
         Direction::Low => "Low",
     };
-
-    let value = match pin_config.get_pin().get_value() {
-        Ok(value) => value,
-        Err(e) => {
-            println!("ERROR: {:?}", e);
-            exit(1);
-        }
+    let edge = status.edge.map_or("", export::edge_str);
+    let value = match status.value {
+        Some(value) => value.to_string(),
+        None => String::new(),
     };
 
-    for (pos, name) in pin_config.names.iter().enumerate() {
+    // ad-hoc pins have no names but still get a row
+    let mut names: Vec<&str> = status.names.iter().map(|n| &n[..]).collect();
+    if names.is_empty() {
+        names.push("");
+    }
+    for (pos, name) in names.into_iter().enumerate() {
         if pos == 0 {
             println!(
-                "| {:<10} | {:<10} | {:<10} | {:<10} | {:<10} | {:<10} |",
-                pin_config.num, pin_config.export, direction, pin_config.active_low, name, value
+                "| {:<10} | {:<10} | {:<10} | {:<10} | {:<10} | {:<10} | {:<10} |",
+                status.num, status.export, direction, status.active_low, edge, name, value
             );
         } else {
             println!(
-                "| {:<10} | {:<10} | {:<10} | {:<10} | {:<10} | {:<10} |",
-                "", "", "", "", name, ""
+                "| {:<10} | {:<10} | {:<10} | {:<10} | {:<10} | {:<10} | {:<10} |",
+                "", "", "", "", "", name, ""
             );
         }
     }
@@ -0,0 +1,55 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::error::*;
+use crate::gpio::Gpio;
+use crate::options::GpioUdevRulesOptions;
+use crate::udev;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+pub fn main(gpio: &Gpio, opts: &GpioUdevRulesOptions) -> Result<()> {
+    let rules = udev::generate_rules(gpio.config());
+
+    if let Some(path) = opts.check {
+        let installed = match fs::read_to_string(path) {
+            Ok(installed) => installed,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(Error::context(
+                    format_args!("Error reading installed udev rules {:?}", path),
+                    e,
+                ))
+            }
+        };
+        match udev::diff(&installed, &rules) {
+            None => println!("udev rules in {:?} are up to date", path),
+            Some(diff) => {
+                println!("--- {}", path);
+                println!("+++ generated");
+                print!("{}", diff);
+                return Err(Error::ChangesPending);
+            }
+        }
+    } else if let Some(path) = opts.install {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                Error::context(format_args!("Error creating directory {:?}", parent), e)
+            })?;
+        }
+        fs::write(path, &rules).map_err(|e| {
+            Error::context(format_args!("Error installing udev rules to {:?}", path), e)
+        })?;
+        println!("Installed udev rules to {:?}", path);
+        println!("Run `udevadm control --reload-rules` for them to take effect");
+    } else {
+        print!("{}", rules);
+    }
+    Ok(())
+}
@@ -6,25 +6,22 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::config::GpioConfig;
-use crate::export;
+use crate::commands;
+use crate::error::*;
+use crate::gpio::Gpio;
 use crate::options::GpioUnexportOptions;
-use std::process::exit;
 
-pub fn main(config: &GpioConfig, opts: &GpioUnexportOptions) {
-    let pin_config = config.get_pin(opts.pin).unwrap_or_else(|| {
-        println!("Unable to find config entry for pin '{}'", opts.pin);
-        exit(1)
-    });
+pub fn main(gpio: &Gpio, opts: &GpioUnexportOptions) -> Result<()> {
+    let pin_config = commands::resolve_pin(gpio, opts.pin, opts.gpio_opts.format)?;
 
     let symlink_root = match opts.symlink_root {
         Some(slr) => slr,
-        None => config.get_symlink_root(),
+        None => gpio.config().get_symlink_root(),
     };
 
-    if let Err(e) = export::unexport(pin_config, Some(symlink_root)) {
-        println!("Error occurred while unexport pin {:?}", pin_config);
-        println!("{}", e);
-        exit(1);
+    if opts.dry_run {
+        commands::print_operations(&gpio.unexport_operations(&pin_config, Some(symlink_root))?);
+        return Ok(());
     }
+    gpio.unexport(&pin_config, Some(symlink_root))
 }
@@ -6,22 +6,29 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::config::GpioConfig;
-use crate::export;
+use crate::commands;
+use crate::error::*;
+use crate::gpio::Gpio;
 use crate::options::GpioUnexportAllOptions;
-use std::process::exit;
 
-pub fn main(config: &GpioConfig, opts: &GpioUnexportAllOptions) {
+pub fn main(gpio: &Gpio, opts: &GpioUnexportAllOptions) -> Result<()> {
+    let config = gpio.config();
     let symlink_root = match opts.symlink_root {
         Some(slr) => slr,
         None => config.get_symlink_root(),
     };
 
-    for pin in config.get_pins().iter().filter(|p| p.export) {
-        if let Err(e) = export::unexport(pin, Some(symlink_root)) {
-            println!("Error occurred while exporting pin: {:?}", pin);
-            println!("{}", e);
-            exit(1);
+    let pins = config
+        .export_order()
+        .map_err(|e| Error::context("Error determining unexport order", e))?;
+
+    // unexport pins before the pins they depend on
+    for pin in pins.into_iter().rev().filter(|p| p.export) {
+        if opts.dry_run {
+            commands::print_operations(&gpio.unexport_operations(pin, Some(symlink_root))?);
+        } else {
+            gpio.unexport(pin, Some(symlink_root))?;
         }
     }
+    Ok(())
 }
@@ -0,0 +1,111 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::commands;
+use crate::error::*;
+use crate::expr::Expr;
+use crate::gpio::{AccessOptions, Gpio, Level};
+use crate::monitor;
+use crate::options::{GpioWaitOptions, OutputFormat};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub fn main(gpio: &Gpio, opts: &GpioWaitOptions) -> Result<()> {
+    let timeout = commands::timeout_from_ms(opts.timeout);
+    let access = AccessOptions {
+        keep: opts.keep,
+        ..Default::default()
+    };
+    match (opts.expr, opts.pin, opts.level) {
+        (Some(expr), _, _) => wait_expr(gpio, opts, expr, timeout, &access),
+        (None, Some(pin), Some(level)) => wait_level(gpio, opts, pin, level, timeout, &access),
+        _ => Err(Error::Msg(String::from(
+            "Either a pin and a level or an expression is required",
+        ))),
+    }
+}
+
+fn wait_level(
+    gpio: &Gpio,
+    opts: &GpioWaitOptions,
+    pin: &str,
+    level: &str,
+    timeout: Option<Duration>,
+    access: &AccessOptions,
+) -> Result<()> {
+    let format = opts.gpio_opts.format;
+    let pin_config = commands::resolve_pin(gpio, pin, format)?;
+    let level = Level::from_str(level)?;
+
+    let result = gpio.wait(&pin_config, level, timeout, access);
+    let value = match result {
+        Ok(value) => Some(value),
+        Err(Error::Timeout { .. }) => None,
+        Err(e) => return Err(e),
+    };
+    commands::print_wait_result(&pin_config, value, format);
+    result.map(|_| ())
+}
+
+fn wait_expr(
+    gpio: &Gpio,
+    opts: &GpioWaitOptions,
+    expr_str: &str,
+    timeout: Option<Duration>,
+    access: &AccessOptions,
+) -> Result<()> {
+    let expr = Expr::from_str(expr_str)?;
+    // reject unknown pins before warning about ad-hoc ones
+    expr.validate(gpio.config())?;
+    for name in expr.pins() {
+        commands::resolve_pin(gpio, name, opts.gpio_opts.format)?;
+    }
+
+    let result = gpio.wait_expr(&expr, timeout, access);
+    let values = match result {
+        Ok(ref values) => Some(values),
+        Err(Error::ExprTimeout { .. }) => None,
+        Err(e) => return Err(e),
+    };
+    print_expr_result(expr_str, values, opts.gpio_opts.format);
+    result.map(|_| ())
+}
+
+fn print_expr_result(expr: &str, values: Option<&BTreeMap<String, u8>>, format: OutputFormat) {
+    let now = monitor::now();
+    match format {
+        OutputFormat::Text => match values {
+            Some(values) => {
+                let values: Vec<String> = values
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect();
+                println!("{}", values.join(" "));
+            }
+            None => println!("TIMEOUT"),
+        },
+        OutputFormat::Json => {
+            let mut object = Map::new();
+            object.insert(String::from("expr"), json!(expr));
+            match values {
+                Some(values) => {
+                    object.insert(String::from("values"), json!(values));
+                    if let Ok((monotonic, realtime)) = now {
+                        commands::timestamps_json(&mut object, monotonic, realtime);
+                    }
+                }
+                None => {
+                    object.insert(String::from("timeout"), json!(true));
+                }
+            }
+            println!("{}", Value::Object(object));
+        }
+    }
+}
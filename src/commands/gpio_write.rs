@@ -6,27 +6,21 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::config::GpioConfig;
+use crate::commands;
+use crate::error::*;
+use crate::gpio::{AccessOptions, Gpio};
 use crate::options::GpioWriteOptions;
-use std::process::exit;
-use sysfs_gpio::Direction;
 
-pub fn main(config: &GpioConfig, opts: &GpioWriteOptions) {
-    let pin_config = match config.get_pin(opts.pin) {
-        Some(pin) => pin,
-        None => {
-            println!("Unable to find config entry for pin '{}'", opts.pin);
-            exit(1);
-        }
+pub fn main(gpio: &Gpio, opts: &GpioWriteOptions) -> Result<()> {
+    let pin_config = commands::resolve_pin(gpio, opts.pin, opts.gpio_opts.format)?;
+    let access = AccessOptions {
+        keep: opts.keep,
+        force: opts.force,
     };
 
-    let pin = pin_config.get_pin();
-    pin.set_direction(Direction::Out).unwrap_or_else(|e| {
-        println!("Error setting GPIO direction: {:?}", e);
-        exit(1)
-    });
-    pin.set_value(opts.value).unwrap_or_else(|e| {
-        println!("There was an error writing to the gpio: {:?}", e);
-        exit(1);
-    });
+    if opts.dry_run {
+        commands::print_operations(&gpio.write_operations(&pin_config, opts.value, &access)?);
+        return Ok(());
+    }
+    gpio.write(&pin_config, opts.value, &access)
 }
@@ -6,11 +6,141 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
+pub mod gpio_apply;
+pub mod gpio_button;
 pub mod gpio_export;
 pub mod gpio_exportall;
+pub mod gpio_gc;
+pub mod gpio_monitor;
 pub mod gpio_poll;
 pub mod gpio_read;
+pub mod gpio_safe_state;
+pub mod gpio_sequence;
+pub mod gpio_state;
 pub mod gpio_status;
+pub mod gpio_udev_rules;
 pub mod gpio_unexport;
 pub mod gpio_unexportall;
+pub mod gpio_wait;
 pub mod gpio_write;
+
+use crate::config::PinConfig;
+use crate::error::*;
+use crate::export::Operation;
+use crate::gpio::Gpio;
+use crate::monitor;
+use crate::options::OutputFormat;
+use serde_json::{json, Map, Value};
+use std::borrow::Cow;
+use std::fmt;
+use std::process::exit;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Report an error in the requested format on stderr and exit with `code`
+///
+/// JSON errors are printed as `{"error": "..."}`.  See `error::EXIT_*` for
+/// the exit codes.
+pub fn exit_with_error(format: OutputFormat, code: i32, msg: impl fmt::Display) -> ! {
+    match format {
+        OutputFormat::Text => eprintln!("{}", msg),
+        OutputFormat::Json => eprintln!("{}", json!({ "error": msg.to_string() })),
+    }
+    exit(code)
+}
+
+/// Print the operations a command would perform (for `--dry-run`)
+///
+/// Each operation is printed on its own line as the equivalent shell command.
+pub fn print_operations(operations: &[Operation]) {
+    for operation in operations {
+        println!("{}", operation);
+    }
+}
+
+/// Look up the pin a command operates on
+///
+/// Prints a warning to stderr if the pin is not configured and default
+/// settings are used instead (see `GpioConfig::resolve_pin`).
+pub fn resolve_pin<'a>(
+    gpio: &'a Gpio,
+    name: &str,
+    format: OutputFormat,
+) -> Result<Cow<'a, PinConfig>> {
+    let pin_config = gpio.pin(name)?;
+    if let Cow::Owned(ref pin_config) = pin_config {
+        let msg = format!(
+            "pin '{}' (gpio{}) is not configured, using default settings",
+            name, pin_config.num
+        );
+        match format {
+            OutputFormat::Text => eprintln!("Warning: {}", msg),
+            OutputFormat::Json => eprintln!("{}", json!({ "warning": msg })),
+        }
+    }
+    Ok(pin_config)
+}
+
+/// Apply `--debounce` and `--min-pulse` overrides to the settings of a pin
+pub fn with_filter(
+    pin_config: &PinConfig,
+    debounce_ms: Option<u64>,
+    min_pulse_ms: Option<u64>,
+) -> PinConfig {
+    PinConfig {
+        debounce_ms: debounce_ms.or(pin_config.debounce_ms),
+        min_pulse_ms: min_pulse_ms.or(pin_config.min_pulse_ms),
+        ..pin_config.clone()
+    }
+}
+
+/// Print the value a pin was waited for, or that the wait timed out
+///
+/// JSON output includes the time at which the value was read.
+pub fn print_wait_result(pin_config: &PinConfig, value: Option<u8>, format: OutputFormat) {
+    let now = monitor::now();
+    match format {
+        OutputFormat::Text => match value {
+            Some(value) => println!("{}", value),
+            None => println!("TIMEOUT"),
+        },
+        OutputFormat::Json => {
+            let mut object = pin_json(pin_config);
+            match value {
+                Some(value) => {
+                    object.insert(String::from("value"), json!(value));
+                    if let Ok((monotonic, realtime)) = now {
+                        timestamps_json(&mut object, monotonic, realtime);
+                    }
+                }
+                None => {
+                    object.insert(String::from("timeout"), json!(true));
+                }
+            }
+            println!("{}", Value::Object(object));
+        }
+    }
+}
+
+/// Convert a `--timeout` in milliseconds, negative values waiting forever
+pub fn timeout_from_ms(timeout_ms: Option<isize>) -> Option<Duration> {
+    timeout_ms
+        .and_then(|ms| u64::try_from(ms).ok())
+        .map(Duration::from_millis)
+}
+
+/// JSON object identifying a pin (`pin` number and `names`)
+pub fn pin_json(pin_config: &PinConfig) -> Map<String, Value> {
+    let mut object = Map::new();
+    object.insert(String::from("pin"), json!(pin_config.num));
+    object.insert(String::from("names"), json!(pin_config.names));
+    object
+}
+
+/// Timestamps in JSON output, as (fractional) seconds
+pub fn timestamps_json(object: &mut Map<String, Value>, monotonic: Duration, realtime: SystemTime) {
+    let realtime = realtime
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO);
+    object.insert(String::from("monotonic"), json!(monotonic.as_secs_f64()));
+    object.insert(String::from("realtime"), json!(realtime.as_secs_f64()));
+}
@@ -6,19 +6,23 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use crate::error::*;
+use crate::export::ReadyWait;
 use glob::glob;
 use serde_derive::Deserialize;
+use std::borrow::Cow;
 use std::collections::{BTreeSet, HashMap};
 use std::fmt;
 use std::fs::{self, File};
-use std::io;
 use std::io::prelude::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 use sysfs_gpio;
 use toml;
 
 const DEFAULT_SYMLINK_ROOT: &str = "/var/run/gpio";
+const SYSFS_GPIO_DEVICES: &str = "/sys/bus/gpio/devices";
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Direction(pub sysfs_gpio::Direction);
@@ -36,6 +40,19 @@ pub enum DirectionDef {
     Low,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(remote = "sysfs_gpio::Edge")]
+pub enum EdgeDef {
+    #[serde(rename = "none")]
+    NoInterrupt,
+    #[serde(rename = "rising")]
+    RisingEdge,
+    #[serde(rename = "falling")]
+    FallingEdge,
+    #[serde(rename = "both")]
+    BothEdges,
+}
+
 impl From<sysfs_gpio::Direction> for Direction {
     fn from(e: sysfs_gpio::Direction) -> Self {
         Direction(e)
@@ -45,9 +62,9 @@ impl From<sysfs_gpio::Direction> for Direction {
 #[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct PinConfig {
     pub num: u64,
-    #[serde(default = "default_direction")]
-    #[serde(with = "DirectionDef")]
-    pub direction: sysfs_gpio::Direction,
+    /// Direction of the pin (see `get_direction` for the default)
+    #[serde(default, deserialize_with = "deserialize_direction")]
+    pub direction: Option<sysfs_gpio::Direction>,
     #[serde(default)]
     pub names: BTreeSet<String>,
     #[serde(default = "bool_true")]
@@ -57,10 +74,49 @@ pub struct PinConfig {
     pub user: Option<String>,
     pub group: Option<String>,
     pub mode: Option<u32>,
+    #[serde(default)]
+    pub after: Vec<String>,
+    pub delay_ms: Option<u64>,
+    pub safe_value: Option<u8>,
+    pub initial_value: Option<u8>,
+    /// Whether `gpio write` may write to the pin (`true` unless set)
+    pub writable: Option<bool>,
+    pub debounce_ms: Option<u64>,
+    pub min_pulse_ms: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_edge")]
+    pub edge: Option<sysfs_gpio::Edge>,
+    pub long_press_ms: Option<u64>,
+    pub double_click_ms: Option<u64>,
+    /// Commands `gpio button` runs for the gestures
+    pub on_short_press: Option<String>,
+    pub on_long_press: Option<String>,
+    pub on_double_click: Option<String>,
+}
+
+fn deserialize_direction<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<sysfs_gpio::Direction>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(with = "DirectionDef")] sysfs_gpio::Direction);
+
+    let direction: Option<Wrapper> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(direction.map(|Wrapper(direction)| direction))
 }
 
-fn default_direction() -> sysfs_gpio::Direction {
-    sysfs_gpio::Direction::In
+fn deserialize_edge<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<sysfs_gpio::Edge>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(with = "EdgeDef")] sysfs_gpio::Edge);
+
+    let edge: Option<Wrapper> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(edge.map(|Wrapper(edge)| edge))
 }
 
 fn bool_true() -> bool {
@@ -72,84 +128,302 @@ pub struct GpioConfig {
     pub pins: Vec<PinConfig>,
     #[serde(default)]
     pub config: SysConfig,
+    #[serde(default)]
+    pub sequences: Vec<SequenceConfig>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
-pub struct SysConfig {
-    pub symlink_root: Option<String>,
+/// A named list of steps run by `gpio sequence <name>`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SequenceConfig {
+    pub name: String,
+    pub steps: Vec<SequenceStep>,
 }
 
-#[derive(Debug)]
-pub enum Error {
-    IoError(io::Error),
-    ParserErrors(toml::de::Error),
-    NoConfigFound,
-    DuplicateNames(String),
+/// A single step of a sequence
+///
+/// Pins are referred to by name (or number).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum SequenceStep {
+    /// Set the value of an output, e.g. `{ set = "vdd_en", value = 1 }`
+    Set { set: String, value: u8 },
+    /// Wait for input to have a value, e.g.
+    /// `{ wait_for = "pgood", value = 1, timeout_ms = 100 }`
+    WaitFor {
+        wait_for: String,
+        value: u8,
+        timeout_ms: Option<u64>,
+    },
+    /// Wait for some time, e.g. `{ wait_ms = 20 }`
+    Wait { wait_ms: u64 },
 }
 
-impl fmt::Display for Error {
+impl fmt::Display for SequenceStep {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Error::IoError(ref e) => e.fmt(f),
-            Error::ParserErrors(ref e) => e.fmt(f),
-            Error::NoConfigFound => write!(f, "No Config Found"),
-            Error::DuplicateNames(ref e) => e.fmt(f),
+            SequenceStep::Set { ref set, value } => write!(f, "set {} = {}", set, value),
+            SequenceStep::WaitFor {
+                ref wait_for,
+                value,
+                timeout_ms,
+            } => {
+                write!(f, "wait for {} == {}", wait_for, value)?;
+                if let Some(timeout_ms) = timeout_ms {
+                    write!(f, " (timeout {}ms)", timeout_ms)?;
+                }
+                Ok(())
+            }
+            SequenceStep::Wait { wait_ms } => write!(f, "wait {}ms", wait_ms),
         }
     }
 }
 
-impl From<io::Error> for Error {
-    fn from(e: io::Error) -> Self {
-        Error::IoError(e)
-    }
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct SysConfig {
+    pub symlink_root: Option<String>,
+    pub ready_timeout_ms: Option<u64>,
+    pub ready_backoff_ms: Option<u64>,
+    pub ready_max_backoff_ms: Option<u64>,
+    pub strict: Option<bool>,
 }
 
 impl PinConfig {
+    /// Create the config of a pin with default settings
+    pub fn new(num: u64) -> PinConfig {
+        PinConfig {
+            num,
+            direction: None,
+            names: BTreeSet::new(),
+            export: true,
+            active_low: false,
+            user: None,
+            group: None,
+            mode: None,
+            after: Vec::new(),
+            delay_ms: None,
+            safe_value: None,
+            initial_value: None,
+            writable: None,
+            debounce_ms: None,
+            min_pulse_ms: None,
+            edge: None,
+            long_press_ms: None,
+            double_click_ms: None,
+            on_short_press: None,
+            on_long_press: None,
+            on_double_click: None,
+        }
+    }
+
+    /// Get the configured direction, `in` unless it is set
+    ///
+    /// Only an explicitly configured `in` makes `gpio write` refuse the pin
+    /// (see `export::check_writable`).
+    pub fn get_direction(&self) -> sysfs_gpio::Direction {
+        self.direction.unwrap_or(sysfs_gpio::Direction::In)
+    }
+
     /// Get the `sysfs_gpio::Pin` to go along with this config`
     pub fn get_pin(&self) -> sysfs_gpio::Pin {
         sysfs_gpio::Pin::new(self.num)
     }
 }
 
+/// Whether `name` is valid as the name of a pin
+///
+/// Names become symlinks in the symlink root and end up in the generated
+/// udev rules, so path separators, quotes, `%` and control characters are
+/// not allowed.
+fn is_valid_pin_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_control() || matches!(c, '/' | '"' | '\'' | '%'))
+}
+
+/// Whether `name` is a valid user or group name (`[a-z_][a-z0-9_-]*`)
+fn is_valid_owner_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_lowercase() || c == '_' => {
+            chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+        }
+        _ => false,
+    }
+}
+
 impl FromStr for GpioConfig {
     type Err = Error;
     /// Load a GPIO configuration for the provided toml string
-    fn from_str(config: &str) -> Result<Self, Error> {
-        let cfg = toml::from_str(config);
-        match cfg {
-            Ok(cfg) => {
-                let val_config: GpioConfig = toml::from_str(config).unwrap();
-                val_config.validate()?;
-                Ok(cfg)
-            }
-            Err(e) => Err(Error::ParserErrors(e)),
-        }
+    fn from_str(config: &str) -> Result<Self> {
+        let cfg: GpioConfig =
+            toml::from_str(config).map_err(|e| Error::ConfigParse { source: e })?;
+        cfg.validate()?;
+        Ok(cfg)
     }
 }
 
 impl GpioConfig {
     /// Validate invariants on the config that cannot easily be done earlier
     ///
-    /// Currently, this just checks that there are no duplicated names between
-    /// different pins in the config
-    fn validate(&self) -> Result<(), Error> {
+    /// Currently, this checks that there are no duplicated names between
+    /// different pins in the config, that pin names as well as user and group
+    /// names are valid (they end up in the generated udev rules), that safe
+    /// and initial values are 0 or 1 and that there are no cycles in the
+    /// `after` dependencies between pins.
+    /// Dependencies on pins which are not part of this config are not checked
+    /// as they may be provided by another config file (see `export_order`).
+    fn validate(&self) -> Result<()> {
         let mut all_names: HashMap<&str, &PinConfig> = HashMap::new();
         for pin in &self.pins {
             for name in &pin.names {
+                if !is_valid_pin_name(name) {
+                    return Err(Error::InvalidName {
+                        pin: pin.num,
+                        name: name.clone(),
+                    });
+                }
                 if let Some(other_pin) = all_names.get(&name[..]) {
-                    return Err(Error::DuplicateNames(format!(
-                        "Pins {} and {} share duplicate \
-                         name '{}'",
-                        pin.num, other_pin.num, name
-                    )));
+                    return Err(Error::DuplicateName {
+                        name: name.clone(),
+                        pin: pin.num,
+                        other_pin: other_pin.num,
+                    });
                 }
                 all_names.insert(&name[..], pin);
             }
+            for name in pin.user.iter().chain(pin.group.iter()) {
+                if !is_valid_owner_name(name) {
+                    return Err(Error::InvalidOwner {
+                        pin: pin.num,
+                        name: name.clone(),
+                    });
+                }
+            }
+            let values = [
+                ("safe_value", pin.safe_value),
+                ("initial_value", pin.initial_value),
+            ];
+            for (setting, value) in values {
+                match value {
+                    Some(value) if value > 1 => {
+                        return Err(Error::InvalidValue {
+                            pin: pin.num,
+                            setting,
+                            value,
+                        })
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        self.check_dependency_cycles()
+    }
+
+    /// Find the index of the pin a dependency refers to (by name or number)
+    fn dependency_index(&self, name: &str) -> Option<usize> {
+        self.get_pin(name)
+            .and_then(|pin| self.pins.iter().position(|p| p.num == pin.num))
+    }
+
+    fn check_dependency_cycles(&self) -> Result<()> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            Unvisited,
+            InProgress,
+            Done,
+        }
+
+        fn visit(
+            config: &GpioConfig,
+            idx: usize,
+            marks: &mut [Mark],
+            path: &mut Vec<usize>,
+        ) -> Result<()> {
+            match marks[idx] {
+                Mark::Done => return Ok(()),
+                Mark::InProgress => {
+                    let start = path.iter().position(|&i| i == idx).unwrap_or(0);
+                    let pins = path[start..]
+                        .iter()
+                        .chain(Some(&idx))
+                        .map(|&i| config.pins[i].num)
+                        .collect();
+                    return Err(Error::DependencyCycle { pins });
+                }
+                Mark::Unvisited => (),
+            }
+
+            marks[idx] = Mark::InProgress;
+            path.push(idx);
+            for dependency in &config.pins[idx].after {
+                if let Some(dep_idx) = config.dependency_index(dependency) {
+                    visit(config, dep_idx, marks, path)?;
+                }
+            }
+            path.pop();
+            marks[idx] = Mark::Done;
+
+            Ok(())
+        }
+
+        let mut marks = vec![Mark::Unvisited; self.pins.len()];
+        let mut path = Vec::new();
+        for idx in 0..self.pins.len() {
+            visit(self, idx, &mut marks, &mut path)?;
         }
 
         Ok(())
     }
 
+    /// Get all pins ordered such that each pin comes after its dependencies
+    ///
+    /// Apart from that, pins are kept in the order of the config.  Returns
+    /// an error if a pin depends on a pin which is not part of the config.
+    pub fn export_order(&self) -> Result<Vec<&PinConfig>> {
+        let mut dependencies = Vec::with_capacity(self.pins.len());
+        for pin in &self.pins {
+            let mut deps = Vec::new();
+            for dependency in &pin.after {
+                match self.dependency_index(dependency) {
+                    Some(idx) => deps.push(idx),
+                    None => {
+                        return Err(Error::UnknownDependency {
+                            pin: pin.num,
+                            dependency: dependency.clone(),
+                        })
+                    }
+                }
+            }
+            dependencies.push(deps);
+        }
+
+        // repeatedly take the first pin whose dependencies are all done;
+        // validation guarantees that there are no cycles
+        let mut done = vec![false; self.pins.len()];
+        let mut order = Vec::with_capacity(self.pins.len());
+        while order.len() < self.pins.len() {
+            let next = (0..self.pins.len())
+                .find(|&i| !done[i] && dependencies[i].iter().all(|&d| done[d]))
+                .ok_or_else(|| Error::DependencyCycle {
+                    pins: (0..self.pins.len())
+                        .filter(|&i| !done[i])
+                        .map(|i| self.pins[i].num)
+                        .collect(),
+                })?;
+            done[next] = true;
+            order.push(&self.pins[next]);
+        }
+
+        Ok(order)
+    }
+
+    /// Get the sequence with the provided name if present in this configuration
+    pub fn get_sequence(&self, name: &str) -> Option<&SequenceConfig> {
+        self.sequences.iter().find(|s| s.name == name)
+    }
+
     /// Load a GPIO Config from the system
     ///
     /// This function will load the GPIO configuration from standard system
@@ -166,44 +440,46 @@ impl GpioConfig {
     ///
     /// Each config file found in these locations will be loaded and then they
     /// will be pulled together to form a unified configuration via the
-    /// `combine` method.
-    pub fn load(configs: &[String]) -> Result<GpioConfig, Error> {
-        let mut config_instances: Vec<GpioConfig> = Vec::new();
+    /// `combine` method.  Errors are reported as `Error::ConfigFile`, with
+    /// the path of the file which could not be loaded or merged.
+    pub fn load(configs: &[String]) -> Result<GpioConfig> {
+        let mut paths: Vec<PathBuf> = Vec::new();
 
         // check /etc/gpio.toml
         if fs::metadata("/etc/gpio.toml").is_ok() {
-            config_instances.push(Self::from_file("/etc/gpio.toml")?);
+            paths.push(PathBuf::from("/etc/gpio.toml"));
         }
         // /etc/gpio.d/*.toml
-        for fragment in glob("/etc/gpio.d/*.toml").unwrap().filter_map(Result::ok) {
-            config_instances.push(Self::from_file(fragment)?);
-        }
+        paths.extend(glob("/etc/gpio.d/*.toml").unwrap().filter_map(|p| p.ok()));
 
         // additional from command-line
-        for fragment in configs {
-            config_instances.push(Self::from_file(fragment)?);
-        }
+        paths.extend(configs.iter().map(PathBuf::from));
 
-        if config_instances.is_empty() {
-            Err(Error::NoConfigFound)
-        } else {
-            let mut cfg = config_instances.remove(0);
-            for higher_priority_cfg in config_instances {
-                cfg.update(higher_priority_cfg)?;
+        let mut cfg: Option<GpioConfig> = None;
+        for path in paths {
+            let higher_priority_cfg = Self::from_file(&path)?;
+            match cfg {
+                Some(ref mut cfg) => cfg
+                    .update(higher_priority_cfg)
+                    .map_err(|e| config_file_error(&path, e))?,
+                None => cfg = Some(higher_priority_cfg),
             }
-            Ok(cfg)
         }
+        cfg.ok_or(Error::NoConfigFound)
     }
 
     /// Load a GPIO config from the specified path
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<GpioConfig, Error> {
-        let mut contents = String::new();
-        let mut f = File::open(path)?;
-        f.read_to_string(&mut contents)?;
-        let config = GpioConfig::from_str(&contents[..])?;
-        config.validate()?;
-
-        Ok(config)
+    ///
+    /// Errors are reported as `Error::ConfigFile` with the path.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<GpioConfig> {
+        let path = path.as_ref();
+        let load = || -> Result<GpioConfig> {
+            let mut contents = String::new();
+            let mut f = File::open(path)?;
+            f.read_to_string(&mut contents)?;
+            GpioConfig::from_str(&contents[..])
+        };
+        load().map_err(|e| config_file_error(path, e))
     }
 
     /// Get the pin with the provided name if present in this configuration
@@ -220,6 +496,42 @@ impl GpioConfig {
         }
     }
 
+    /// Get the config of a pin, falling back to an ad-hoc pin
+    ///
+    /// In addition to the names and numbers accepted by `get_pin`, pins may
+    /// be given as `<chip>:<line>` (e.g. `gpiochip1:13`).  Pins which are not
+    /// configured are returned with default settings (see `PinConfig::new`),
+    /// unless `strict` is set in the `[config]` section.
+    pub fn resolve_pin(&self, name: &str) -> Result<Cow<'_, PinConfig>> {
+        if let Some(pin) = self.get_pin(name) {
+            return Ok(Cow::Borrowed(pin));
+        }
+
+        let num = match name.split_once(':') {
+            Some((chip, line)) => {
+                Some(chip_line_to_num(Path::new(SYSFS_GPIO_DEVICES), chip, line)?)
+            }
+            None => name.parse::<u64>().ok(),
+        };
+        if let Some(pin) = num.and_then(|num| self.pins.iter().find(|p| p.num == num)) {
+            return Ok(Cow::Borrowed(pin));
+        }
+        match num {
+            Some(num) if !self.is_strict() => Ok(Cow::Owned(PinConfig::new(num))),
+            Some(_) => Err(Error::NotConfigured {
+                name: name.to_string(),
+            }),
+            None => Err(Error::UnknownPin {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Whether only configured pins may be used (`strict` in `[config]`)
+    pub fn is_strict(&self) -> bool {
+        self.config.strict.unwrap_or(false)
+    }
+
     /// Get a reference to all the pins in this config
     pub fn get_pins(&self) -> &[PinConfig] {
         &self.pins[..]
@@ -233,21 +545,107 @@ impl GpioConfig {
         }
     }
 
+    /// Get the parameters for waiting on freshly exported pins
+    ///
+    /// Settings not specified in the config fall back to the defaults of
+    /// `ReadyWait`.
+    pub fn get_ready_wait(&self) -> ReadyWait {
+        let default = ReadyWait::default();
+        ReadyWait {
+            timeout: self
+                .config
+                .ready_timeout_ms
+                .map_or(default.timeout, Duration::from_millis),
+            initial_backoff: self
+                .config
+                .ready_backoff_ms
+                .map_or(default.initial_backoff, Duration::from_millis),
+            max_backoff: self
+                .config
+                .ready_max_backoff_ms
+                .map_or(default.max_backoff, Duration::from_millis),
+        }
+    }
+
     /// Merge other into self (takes ownership of other)
     ///
     /// If in conflict, the other GPIO config takes priority.
-    pub fn update(&mut self, other: GpioConfig) -> Result<(), Error> {
+    pub fn update(&mut self, other: GpioConfig) -> Result<()> {
         if let Some(symlink_root) = other.config.symlink_root {
             self.config.symlink_root = Some(symlink_root);
         }
+        if let Some(ready_timeout_ms) = other.config.ready_timeout_ms {
+            self.config.ready_timeout_ms = Some(ready_timeout_ms);
+        }
+        if let Some(ready_backoff_ms) = other.config.ready_backoff_ms {
+            self.config.ready_backoff_ms = Some(ready_backoff_ms);
+        }
+        if let Some(ready_max_backoff_ms) = other.config.ready_max_backoff_ms {
+            self.config.ready_max_backoff_ms = Some(ready_max_backoff_ms);
+        }
+        if let Some(strict) = other.config.strict {
+            self.config.strict = Some(strict);
+        }
         for other_pin in other.pins {
             // determine the case we are dealing with
             let existing = match self.pins.iter_mut().find(|p| p.num == other_pin.num) {
                 Some(pin) => {
                     pin.names.extend(other_pin.names.clone());
-                    pin.direction = other_pin.direction;
+                    if other_pin.direction.is_some() {
+                        pin.direction = other_pin.direction;
+                    }
                     pin.export = other_pin.export;
                     pin.active_low = other_pin.active_low;
+                    if other_pin.user.is_some() {
+                        pin.user = other_pin.user.clone();
+                    }
+                    if other_pin.group.is_some() {
+                        pin.group = other_pin.group.clone();
+                    }
+                    if other_pin.mode.is_some() {
+                        pin.mode = other_pin.mode;
+                    }
+                    for dependency in &other_pin.after {
+                        if !pin.after.contains(dependency) {
+                            pin.after.push(dependency.clone());
+                        }
+                    }
+                    if other_pin.delay_ms.is_some() {
+                        pin.delay_ms = other_pin.delay_ms;
+                    }
+                    if other_pin.writable.is_some() {
+                        pin.writable = other_pin.writable;
+                    }
+                    if other_pin.safe_value.is_some() {
+                        pin.safe_value = other_pin.safe_value;
+                    }
+                    if other_pin.initial_value.is_some() {
+                        pin.initial_value = other_pin.initial_value;
+                    }
+                    if other_pin.debounce_ms.is_some() {
+                        pin.debounce_ms = other_pin.debounce_ms;
+                    }
+                    if other_pin.min_pulse_ms.is_some() {
+                        pin.min_pulse_ms = other_pin.min_pulse_ms;
+                    }
+                    if other_pin.edge.is_some() {
+                        pin.edge = other_pin.edge;
+                    }
+                    if other_pin.long_press_ms.is_some() {
+                        pin.long_press_ms = other_pin.long_press_ms;
+                    }
+                    if other_pin.double_click_ms.is_some() {
+                        pin.double_click_ms = other_pin.double_click_ms;
+                    }
+                    if other_pin.on_short_press.is_some() {
+                        pin.on_short_press = other_pin.on_short_press.clone();
+                    }
+                    if other_pin.on_long_press.is_some() {
+                        pin.on_long_press = other_pin.on_long_press.clone();
+                    }
+                    if other_pin.on_double_click.is_some() {
+                        pin.on_double_click = other_pin.on_double_click.clone();
+                    }
                     true
                 }
                 None => false,
@@ -257,21 +655,79 @@ impl GpioConfig {
                 self.pins.push(other_pin);
             }
         }
+        for other_sequence in other.sequences {
+            match self
+                .sequences
+                .iter_mut()
+                .find(|s| s.name == other_sequence.name)
+            {
+                Some(sequence) => *sequence = other_sequence,
+                None => self.sequences.push(other_sequence),
+            }
+        }
 
         // validate the resulting structure
         self.validate()
     }
 }
 
+/// Translate a line of a GPIO chip into its global GPIO number
+///
+/// `devices` is the sysfs directory of the GPIO bus devices, which contains
+/// `<chip>/gpio/gpiochip<base>/{base,ngpio}` for each chip.
+fn chip_line_to_num(devices: &Path, chip: &str, line: &str) -> Result<u64> {
+    let invalid = |reason: String| Error::InvalidPin {
+        name: format!("{}:{}", chip, line),
+        reason,
+    };
+    let line_num: u64 = line
+        .parse()
+        .map_err(|_| invalid(format!("invalid line '{}'", line)))?;
+    let pattern = devices.join(chip).join("gpio").join("gpiochip*");
+    let dir = glob(&pattern.to_string_lossy())
+        .ok()
+        .and_then(|mut paths| paths.find_map(|p| p.ok()))
+        .ok_or_else(|| invalid(format!("no GPIO chip '{}' found", chip)))?;
+
+    let read_number = |attribute: &str| -> Result<u64> {
+        let value = fs::read_to_string(dir.join(attribute))?;
+        value.trim().parse().map_err(|_| {
+            invalid(format!(
+                "invalid {} '{}' of {}",
+                attribute,
+                value.trim(),
+                chip
+            ))
+        })
+    };
+    let (base, ngpio) = (read_number("base")?, read_number("ngpio")?);
+    if line_num >= ngpio {
+        return Err(invalid(format!(
+            "line out of range for {} ({} lines)",
+            chip, ngpio
+        )));
+    }
+    Ok(base + line_num)
+}
+
+fn config_file_error(path: &Path, e: Error) -> Error {
+    Error::ConfigFile {
+        path: path.to_path_buf(),
+        source: Box::new(e),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::test_util::TempDir;
     use std::collections::BTreeSet;
     use std::iter::FromIterator;
     use std::str::FromStr;
     use sysfs_gpio::Direction as D;
+    use sysfs_gpio::Edge;
 
-    const BASIC_CFG: &'static str = r#"
+    const BASIC_CFG: &str = r#"
 [[pins]]
 num = 73
 names = ["reset_button"]
@@ -285,7 +741,7 @@ names = ["status_led", "A27", "green_led"]
 direction = "out"
 "#;
 
-    const COMPACT_CFG: &'static str = r#"
+    const COMPACT_CFG: &str = r#"
 pins = [
    { num = 73, names = ["reset_button"], direction = "in", active_low = true, export = true},
    { num = 37, names = ["status_led", "A27", "green_led"], direction = "out"},
@@ -295,12 +751,20 @@ pins = [
 symlink_root = "/tmp/gpio"
 "#;
 
-    const MISSING_PINNUM_CFG: &'static str = r#"
+    const READY_WAIT_CFG: &str = r#"
+pins = []
+
+[config]
+ready_timeout_ms = 250
+ready_backoff_ms = 2
+"#;
+
+    const MISSING_PINNUM_CFG: &str = r#"
 [[pins]]
 export = true
 "#;
 
-    const DUPLICATED_NAMES_CFG: &'static str = r#"
+    const DUPLICATED_NAMES_CFG: &str = r#"
 [[pins]]
 num = 25
 names = ["foo", "bar"]
@@ -310,7 +774,7 @@ num = 26
 names = ["baz", "foo"]  # foo is repeated!
 "#;
 
-    const PARTIALLY_OVERLAPS_BASIC_CFG: &'static str = r#"
+    const PARTIALLY_OVERLAPS_BASIC_CFG: &str = r#"
 [config]
 symlink_root = "/foo/bar/baz"
 
@@ -329,6 +793,84 @@ direction = "in"
 [[pins]]
 num = 88
 names = ["wildcard"]
+"#;
+
+    const EDGE_CFG: &str = r#"
+[[pins]]
+num = 73
+names = ["reset_button"]
+edge = "both"
+
+[[pins]]
+num = 37
+names = ["status_led"]
+direction = "out"
+"#;
+
+    const EDGE_OVERRIDE_CFG: &str = r#"
+[[pins]]
+num = 73
+names = ["reset_button"]
+
+[[pins]]
+num = 37
+direction = "in"
+edge = "rising"
+"#;
+
+    const BUTTON_CFG: &str = r#"
+[[pins]]
+num = 73
+names = ["reset_button"]
+active_low = true
+on_short_press = "systemctl restart app"
+double_click_ms = 0
+"#;
+
+    const BUTTON_OVERRIDE_CFG: &str = r#"
+[[pins]]
+num = 73
+long_press_ms = 1500
+on_long_press = "systemctl reboot"
+"#;
+
+    const DEPENDENCIES_CFG: &str = r#"
+[[pins]]
+num = 40
+names = ["enable"]
+after = ["reset"]
+delay_ms = 50
+writable = false
+debounce_ms = 20
+min_pulse_ms = 5
+
+[[pins]]
+num = 41
+names = ["reset"]
+direction = "out"
+active_low = true
+safe_value = 0
+initial_value = 0
+
+[[sequences]]
+name = "power_on"
+steps = [
+    { set = "reset", value = 1 },
+    { wait_ms = 10 },
+    { wait_for = "enable", value = 1, timeout_ms = 500 },
+]
+"#;
+
+    const DEPENDENCY_CYCLE_CFG: &str = r#"
+[[pins]]
+num = 37
+names = ["a"]
+after = ["b"]
+
+[[pins]]
+num = 38
+names = ["b"]
+after = ["a"]
 "#;
 
     #[test]
@@ -343,20 +885,20 @@ names = ["wildcard"]
 
         assert_eq!(config.get_symlink_root(), "/var/run/gpio");
 
-        let reset_button = config.pins.get(0).unwrap();
+        let reset_button = config.pins.first().unwrap();
         assert_eq!(reset_button.num, 73);
         assert_eq!(
             reset_button.names,
             BTreeSet::from_iter(vec![String::from("reset_button")])
         );
-        assert_eq!(reset_button.direction, D::In);
-        assert_eq!(reset_button.active_low, true);
-        assert_eq!(reset_button.export, true);
+        assert_eq!(reset_button.direction, Some(D::In));
+        assert!(reset_button.active_low);
+        assert!(reset_button.export);
 
         assert_eq!(status_led.names, names);
-        assert_eq!(status_led.direction, D::Out);
-        assert_eq!(status_led.active_low, false);
-        assert_eq!(status_led.export, true);
+        assert_eq!(status_led.direction, Some(D::Out));
+        assert!(!status_led.active_low);
+        assert!(status_led.export);
     }
 
     #[test]
@@ -385,6 +927,50 @@ names = ["wildcard"]
         assert_eq!(config.get_pin("64"), None);
     }
 
+    #[test]
+    fn test_resolve_pin() {
+        let config = GpioConfig::from_str(BASIC_CFG).unwrap();
+        assert_eq!(config.resolve_pin("status_led").unwrap().num, 37);
+        assert!(matches!(
+            config.resolve_pin("status_led").unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            config.resolve_pin("45").unwrap(),
+            Cow::<PinConfig>::Owned(PinConfig::new(45))
+        );
+        match config.resolve_pin("missing") {
+            Err(Error::UnknownPin { .. }) => (),
+            r => panic!("Expected UnknownPin Error, got {:?}", r),
+        }
+
+        let mut config = config;
+        config.config.strict = Some(true);
+        assert_eq!(config.resolve_pin("37").unwrap().num, 37);
+        match config.resolve_pin("45") {
+            Err(Error::NotConfigured { ref name }) if name == "45" => (),
+            r => panic!("Expected NotConfigured Error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_chip_line_to_num() {
+        let tmp = TempDir::new("chip-line");
+        let devices = tmp.path();
+        let chip = devices.join("gpiochip1/gpio/gpiochip32");
+        fs::create_dir_all(&chip).unwrap();
+        fs::write(chip.join("base"), "32\n").unwrap();
+        fs::write(chip.join("ngpio"), "16\n").unwrap();
+
+        assert_eq!(chip_line_to_num(devices, "gpiochip1", "13").unwrap(), 45);
+        for (chip, line) in [("gpiochip1", "16"), ("gpiochip1", "x"), ("gpiochip2", "0")] {
+            match chip_line_to_num(devices, chip, line) {
+                Err(Error::InvalidPin { .. }) => (),
+                r => panic!("Expected InvalidPin Error, got {:?}", r),
+            }
+        }
+    }
+
     #[test]
     fn test_parser_compact() {
         let config = GpioConfig::from_str(COMPACT_CFG).unwrap();
@@ -395,10 +981,22 @@ names = ["wildcard"]
             String::from("green_led"),
         ]);
         assert_eq!(status_led.names, names);
-        assert_eq!(status_led.direction, D::Out);
-        assert_eq!(status_led.active_low, false);
-        assert_eq!(status_led.export, true);
-        assert_eq!(config.get_symlink_root(), "/tmp/gpio")
+        assert_eq!(status_led.direction, Some(D::Out));
+        assert!(!status_led.active_low);
+        assert!(status_led.export);
+        assert_eq!(config.get_symlink_root(), "/tmp/gpio");
+    }
+
+    #[test]
+    fn test_ready_wait() {
+        let config = GpioConfig::from_str(READY_WAIT_CFG).unwrap();
+        let ready = config.get_ready_wait();
+        assert_eq!(ready.timeout, Duration::from_millis(250));
+        assert_eq!(ready.initial_backoff, Duration::from_millis(2));
+        assert_eq!(ready.max_backoff, ReadyWait::default().max_backoff);
+
+        let config = GpioConfig::from_str(BASIC_CFG).unwrap();
+        assert_eq!(config.get_ready_wait(), ReadyWait::default());
     }
 
     #[test]
@@ -406,7 +1004,7 @@ names = ["wildcard"]
         let configstr = "";
         match GpioConfig::from_str(configstr) {
             Ok(pins) => assert_eq!(pins.pins, vec![]),
-            Err(Error::ParserErrors(_)) => {}
+            Err(Error::ConfigParse { .. }) => {}
             _ => panic!("Expected a parsing error"),
         }
     }
@@ -414,7 +1012,7 @@ names = ["wildcard"]
     #[test]
     fn test_parser_missing_pinnum() {
         match GpioConfig::from_str(MISSING_PINNUM_CFG) {
-            Err(Error::ParserErrors(_)) => {}
+            Err(Error::ConfigParse { .. }) => {}
             _ => panic!("Expected a parsing error"),
         }
     }
@@ -424,7 +1022,7 @@ names = ["wildcard"]
         // basically, just garbage data
         let configstr = r"[] -*-..asdf=-=-@#$%^&*()";
         match GpioConfig::from_str(configstr) {
-            Err(Error::ParserErrors(_)) => {}
+            Err(Error::ConfigParse { .. }) => {}
             _ => panic!("Did not receive parse error when expected"),
         }
     }
@@ -432,8 +1030,95 @@ names = ["wildcard"]
     #[test]
     fn test_error_on_duplicated_names() {
         match GpioConfig::from_str(DUPLICATED_NAMES_CFG) {
-            Err(Error::DuplicateNames(_)) => (),
-            r => panic!("Expected DuplicateNames Error, got {:?}", r),
+            Err(Error::DuplicateName {
+                ref name,
+                pin: 26,
+                other_pin: 25,
+            }) if name == "foo" => (),
+            r => panic!("Expected DuplicateName Error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_error_on_invalid_name() {
+        for name in [
+            r#""led\nACTION==\"add\", RUN+=\"/bin/reboot\"""#,
+            r#""../led""#,
+            r#""led'""#,
+            r#""led%k""#,
+            r#""""#,
+        ] {
+            let config = GpioConfig::from_str(&format!("[[pins]]\nnum = 73\nnames = [{}]\n", name));
+            match config {
+                Err(Error::InvalidName { pin: 73, .. }) => (),
+                r => panic!("Expected InvalidName Error for {}, got {:?}", name, r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_on_invalid_owner() {
+        let config = GpioConfig::from_str(
+            r#"
+[[pins]]
+num = 73
+user = "gpio-user_1"
+group = "_gpio"
+"#,
+        );
+        assert!(config.is_ok());
+
+        for owner in [
+            r#"user = "root; reboot""#,
+            r#"group = "gpio users""#,
+            r#"group = "'gpio'""#,
+            r#"user = "Gpio""#,
+            r#"user = """#,
+        ] {
+            let config = GpioConfig::from_str(&format!("[[pins]]\nnum = 73\n{}\n", owner));
+            match config {
+                Err(Error::InvalidOwner { pin: 73, .. }) => (),
+                r => panic!("Expected InvalidOwner Error for {}, got {:?}", owner, r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_on_invalid_value() {
+        for setting in ["safe_value", "initial_value"] {
+            let config = GpioConfig::from_str(&format!("[[pins]]\nnum = 41\n{} = 2\n", setting));
+            match config {
+                Err(Error::InvalidValue {
+                    pin: 41,
+                    setting: s,
+                    value: 2,
+                }) if s == setting => (),
+                r => panic!("Expected InvalidValue Error for {}, got {:?}", setting, r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_from_file_has_path() {
+        let tmp = TempDir::new("from-file");
+        let path = tmp.path().join("gpio.toml");
+        fs::write(&path, DUPLICATED_NAMES_CFG).unwrap();
+        match GpioConfig::from_file(&path) {
+            Err(Error::ConfigFile {
+                path: ref error_path,
+                ref source,
+            }) if *error_path == path => match **source {
+                Error::DuplicateName { .. } => (),
+                ref e => panic!("Expected DuplicateName Error, got {:?}", e),
+            },
+            r => panic!("Expected ConfigFile Error, got {:?}", r),
+        }
+
+        match GpioConfig::from_file("/nonexistent/gpio.toml") {
+            Err(ref e @ Error::ConfigFile { .. }) => {
+                assert!(std::error::Error::source(e).is_some());
+            }
+            r => panic!("Expected ConfigFile Error, got {:?}", r),
         }
     }
 
@@ -447,15 +1132,15 @@ names = ["wildcard"]
 
         assert_eq!(config.get_symlink_root(), "/foo/bar/baz");
 
-        let reset_button = config.pins.get(0).unwrap();
+        let reset_button = config.pins.first().unwrap();
         assert_eq!(reset_button.num, 73);
         assert_eq!(
             reset_button.names,
             BTreeSet::from_iter(vec![String::from("reset_button"), String::from("new_name")])
         );
-        assert_eq!(reset_button.direction, D::In);
-        assert_eq!(reset_button.active_low, false);
-        assert_eq!(reset_button.export, true);
+        assert_eq!(reset_button.direction, Some(D::In));
+        assert!(!reset_button.active_low);
+        assert!(reset_button.export);
 
         let status_led = config.pins.get(1).unwrap();
         let names = BTreeSet::from_iter(vec![
@@ -464,9 +1149,9 @@ names = ["wildcard"]
             String::from("green_led"),
         ]);
         assert_eq!(status_led.names, names);
-        assert_eq!(status_led.direction, D::In);
-        assert_eq!(status_led.active_low, false);
-        assert_eq!(status_led.export, true);
+        assert_eq!(status_led.direction, Some(D::In));
+        assert!(!status_led.active_low);
+        assert!(status_led.export);
 
         let wildcard = config.pins.get(2).unwrap();
         assert_eq!(wildcard.num, 88);
@@ -475,4 +1160,108 @@ names = ["wildcard"]
             BTreeSet::from_iter(vec![String::from("wildcard")])
         );
     }
+
+    #[test]
+    fn test_parse_edge() {
+        let mut config = GpioConfig::from_str(EDGE_CFG).unwrap();
+        assert_eq!(config.pins[0].edge, Some(Edge::BothEdges));
+        assert_eq!(config.pins[1].edge, None);
+
+        // fragments without an edge leave it unchanged
+        config
+            .update(GpioConfig::from_str(EDGE_OVERRIDE_CFG).unwrap())
+            .unwrap();
+        assert_eq!(config.pins[0].edge, Some(Edge::BothEdges));
+        assert_eq!(config.pins[1].edge, Some(Edge::RisingEdge));
+
+        match GpioConfig::from_str("[[pins]]\nnum = 73\nedge = \"up\"\n") {
+            Err(Error::ConfigParse { .. }) => (),
+            r => panic!("Expected ConfigParse Error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_parse_button() {
+        let mut config = GpioConfig::from_str(BUTTON_CFG).unwrap();
+        config
+            .update(GpioConfig::from_str(BUTTON_OVERRIDE_CFG).unwrap())
+            .unwrap();
+        let button = config.get_pin("reset_button").unwrap();
+        assert_eq!(button.long_press_ms, Some(1500));
+        assert_eq!(button.double_click_ms, Some(0));
+        assert_eq!(
+            button.on_short_press.as_deref(),
+            Some("systemctl restart app")
+        );
+        assert_eq!(button.on_long_press.as_deref(), Some("systemctl reboot"));
+        assert_eq!(button.on_double_click, None);
+    }
+
+    #[test]
+    fn test_parse_dependencies_and_sequences() {
+        let config = GpioConfig::from_str(DEPENDENCIES_CFG).unwrap();
+        let enable = config.get_pin("enable").unwrap();
+        assert_eq!(enable.after, vec![String::from("reset")]);
+        assert_eq!(enable.delay_ms, Some(50));
+        assert_eq!(enable.safe_value, None);
+        assert_eq!(enable.writable, Some(false));
+        assert_eq!(enable.debounce_ms, Some(20));
+        assert_eq!(enable.min_pulse_ms, Some(5));
+        let reset = config.get_pin("reset").unwrap();
+        assert_eq!(reset.safe_value, Some(0));
+        assert_eq!(reset.initial_value, Some(0));
+        assert_eq!(reset.writable, None);
+        // the raw level is high for an inactive active-low output
+        assert_eq!(crate::export::initial_direction(reset), D::High);
+
+        let seq = config.get_sequence("power_on").unwrap();
+        assert_eq!(
+            seq.steps,
+            vec![
+                SequenceStep::Set {
+                    set: String::from("reset"),
+                    value: 1,
+                },
+                SequenceStep::Wait { wait_ms: 10 },
+                SequenceStep::WaitFor {
+                    wait_for: String::from("enable"),
+                    value: 1,
+                    timeout_ms: Some(500),
+                },
+            ]
+        );
+        assert!(config.get_sequence("power_off").is_none());
+    }
+
+    #[test]
+    fn test_merge_keeps_writable() {
+        let mut config = GpioConfig::from_str(DEPENDENCIES_CFG).unwrap();
+        let cfg2 = GpioConfig::from_str("[[pins]]\nnum = 40\nnames = [\"enable_n\"]\n").unwrap();
+        config.update(cfg2).unwrap();
+        assert_eq!(config.get_pin("enable_n").unwrap().writable, Some(false));
+
+        let cfg3 = GpioConfig::from_str("[[pins]]\nnum = 40\nwritable = true\n").unwrap();
+        config.update(cfg3).unwrap();
+        assert_eq!(config.get_pin("enable").unwrap().writable, Some(true));
+    }
+
+    #[test]
+    fn test_export_order() {
+        let config = GpioConfig::from_str(DEPENDENCIES_CFG).unwrap();
+        let order: Vec<u64> = config
+            .export_order()
+            .unwrap()
+            .iter()
+            .map(|p| p.num)
+            .collect();
+        assert_eq!(order, vec![41, 40]);
+    }
+
+    #[test]
+    fn test_error_on_dependency_cycle() {
+        match GpioConfig::from_str(DEPENDENCY_CYCLE_CFG) {
+            Err(Error::DependencyCycle { ref pins }) if pins.len() > 1 => (),
+            r => panic!("Expected DependencyCycle Error, got {:?}", r),
+        }
+    }
 }
@@ -6,10 +6,40 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use crate::state::Mismatch;
+use nix::errno::Errno;
+use nix::sys::signal::Signal;
 use nix::Error as NixError;
-use std::io::Error as IoError;
+use std::fmt;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::PathBuf;
+use std::time::Duration;
 use sysfs_gpio::Error as GpioError;
 
+/// Exit code for errors not covered by any of the other codes
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code if a pin did not reach the expected state in time
+pub const EXIT_TIMEOUT: i32 = 2;
+/// Exit code if an operation is refused by the configuration (e.g. writing
+/// to an input without `--force`)
+pub const EXIT_REFUSED: i32 = 3;
+/// Exit code if a pin is not configured or does not exist
+pub const EXIT_UNKNOWN_PIN: i32 = 4;
+/// Exit code if access to sysfs (or another file) was denied
+pub const EXIT_PERMISSION_DENIED: i32 = 5;
+/// Exit code if a pin is in use (e.g. requested by a kernel driver)
+pub const EXIT_BUSY: i32 = 6;
+/// Exit code if the configuration is invalid
+pub const EXIT_CONFIG: i32 = 7;
+/// Exit code if reading or writing sysfs attributes failed otherwise
+pub const EXIT_HARDWARE: i32 = 8;
+/// Exit code if the live state differs from the desired one (e.g. `apply
+/// --plan`, `state diff`)
+pub const EXIT_CHANGES_PENDING: i32 = 10;
+/// Base of the exit code if waiting was interrupted by a signal, which is
+/// added to it as usual for shells (e.g. 130 for `SIGINT`)
+pub const EXIT_SIGNAL_BASE: i32 = 128;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error(transparent)]
@@ -18,10 +48,175 @@ pub enum Error {
     Nix(#[from] NixError),
     #[error(transparent)]
     Io(#[from] IoError),
+    #[error("{}: {source}", path.display())]
+    ConfigFile {
+        path: PathBuf,
+        #[source]
+        source: Box<Error>,
+    },
+    #[error("{source}")]
+    ConfigParse {
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error("no config found")]
+    NoConfigFound,
+    #[error("pins {pin} and {other_pin} share duplicate name '{name}'")]
+    DuplicateName {
+        name: String,
+        pin: u64,
+        other_pin: u64,
+    },
+    #[error("pin {pin} has invalid name {name:?}")]
+    InvalidName { pin: u64, name: String },
+    #[error("pin {pin} has invalid user or group name '{name}'")]
+    InvalidOwner { pin: u64, name: String },
+    #[error("pin {pin} has invalid {setting} {value} (expected 0 or 1)")]
+    InvalidValue {
+        pin: u64,
+        setting: &'static str,
+        value: u8,
+    },
+    #[error("dependency cycle between pins {}", join(pins, " -> "))]
+    DependencyCycle { pins: Vec<u64> },
+    #[error("pin {pin} depends on unknown pin '{dependency}'")]
+    UnknownDependency { pin: u64, dependency: String },
+    #[error("gpio{pin}: failed to {operation}: {source}")]
+    PinOperation {
+        pin: u64,
+        operation: &'static str,
+        #[source]
+        source: Box<Error>,
+    },
+    #[error("{context}: {source}")]
+    Context {
+        context: String,
+        #[source]
+        source: Box<Error>,
+    },
+    #[error("unable to find config entry for pin '{name}'")]
+    UnknownPin { name: String },
+    #[error("unable to find config entry for pin '{name}' (strict mode is enabled)")]
+    NotConfigured { name: String },
+    #[error("invalid pin '{name}': {reason}")]
+    InvalidPin { name: String, reason: String },
+    #[error("unknown sequence '{name}'")]
+    UnknownSequence { name: String },
+    #[error("invalid snapshot: {reason}")]
+    InvalidSnapshot { reason: String },
+    #[error(
+        "snapshot does not match the configuration: {}",
+        join(mismatches, "; ")
+    )]
+    SnapshotMismatch { mismatches: Vec<Mismatch> },
+    #[error(
+        "gpio{pin}: attribute '{attribute}' did not become ready within {timeout:?} ({source})"
+    )]
+    NotReady {
+        pin: u64,
+        attribute: &'static str,
+        timeout: Duration,
+        #[source]
+        source: NixError,
+    },
+    #[error("refusing to replace {path:?}: not a symlink")]
+    NotASymlink { path: PathBuf },
+    #[error("refusing to write to gpio{pin}: {reason} (use --force to override)")]
+    NotWritable { pin: u64, reason: &'static str },
+    #[error("timed out waiting for gpio{pin} to become {value}")]
+    Timeout { pin: u64, value: u8 },
+    #[error("timed out waiting for an edge on gpio{pin}")]
+    PollTimeout { pin: u64 },
+    #[error("timed out waiting for {expr}")]
+    ExprTimeout { expr: String },
+    #[error("invalid expression at position {position}: {reason}")]
+    InvalidExpression { position: usize, reason: String },
+    #[error("the live state differs from the desired one")]
+    ChangesPending,
+    #[error("interrupted by {signal}")]
+    Interrupted { signal: Signal },
     #[error("{0}")]
     Msg(String),
 }
 
+impl Error {
+    /// Attach the pin and the operation on it during which `source` occurred
+    pub fn pin_operation(pin: u64, operation: &'static str, source: impl Into<Error>) -> Error {
+        Error::PinOperation {
+            pin,
+            operation,
+            source: Box::new(source.into()),
+        }
+    }
+
+    /// Describe what was being done when `source` occurred (e.g. which file
+    /// was being read)
+    pub fn context(context: impl fmt::Display, source: impl Into<Error>) -> Error {
+        Error::Context {
+            context: context.to_string(),
+            source: Box::new(source.into()),
+        }
+    }
+
+    /// Exit code of the command line tool for this error (see `EXIT_*`)
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            Error::Gpio(GpioError::Io(ref e)) | Error::Io(ref e) => io_exit_code(e),
+            Error::Gpio(_) => EXIT_HARDWARE,
+            Error::Nix(errno) => errno_exit_code(errno),
+            Error::UnknownPin { .. } | Error::NotConfigured { .. } | Error::InvalidPin { .. } => {
+                EXIT_UNKNOWN_PIN
+            }
+            Error::ConfigFile { .. }
+            | Error::ConfigParse { .. }
+            | Error::NoConfigFound
+            | Error::DuplicateName { .. }
+            | Error::InvalidName { .. }
+            | Error::InvalidOwner { .. }
+            | Error::InvalidValue { .. }
+            | Error::DependencyCycle { .. }
+            | Error::UnknownDependency { .. }
+            | Error::UnknownSequence { .. }
+            | Error::InvalidSnapshot { .. }
+            | Error::SnapshotMismatch { .. } => EXIT_CONFIG,
+            Error::PinOperation { ref source, .. } | Error::Context { ref source, .. } => {
+                source.exit_code()
+            }
+            Error::NotReady { .. }
+            | Error::Timeout { .. }
+            | Error::PollTimeout { .. }
+            | Error::ExprTimeout { .. } => EXIT_TIMEOUT,
+            Error::NotWritable { .. } => EXIT_REFUSED,
+            Error::ChangesPending => EXIT_CHANGES_PENDING,
+            Error::Interrupted { signal } => EXIT_SIGNAL_BASE + signal as i32,
+            Error::NotASymlink { .. } | Error::InvalidExpression { .. } | Error::Msg(_) => {
+                EXIT_FAILURE
+            }
+        }
+    }
+}
+
+fn join<T: fmt::Display>(items: &[T], separator: &str) -> String {
+    let items: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+    items.join(separator)
+}
+
+fn errno_exit_code(errno: Errno) -> i32 {
+    match errno {
+        Errno::EACCES | Errno::EPERM => EXIT_PERMISSION_DENIED,
+        Errno::EBUSY => EXIT_BUSY,
+        _ => EXIT_HARDWARE,
+    }
+}
+
+fn io_exit_code(e: &IoError) -> i32 {
+    match e.raw_os_error() {
+        Some(errno) => errno_exit_code(Errno::from_raw(errno)),
+        None if e.kind() == ErrorKind::PermissionDenied => EXIT_PERMISSION_DENIED,
+        None => EXIT_HARDWARE,
+    }
+}
+
 impl From<String> for Error {
     fn from(msg: String) -> Error {
         Error::Msg(msg)
@@ -29,3 +224,51 @@ impl From<String> for Error {
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        let denied = Error::pin_operation(37, "set value", IoError::from_raw_os_error(13));
+        assert_eq!(denied.exit_code(), EXIT_PERMISSION_DENIED);
+        assert_eq!(
+            denied.to_string(),
+            "gpio37: failed to set value: Permission denied (os error 13)"
+        );
+        let busy =
+            Error::pin_operation(37, "export", GpioError::Io(IoError::from_raw_os_error(16)));
+        assert_eq!(busy.exit_code(), EXIT_BUSY);
+        assert_eq!(Error::Nix(Errno::EIO).exit_code(), EXIT_HARDWARE);
+        assert_eq!(
+            Error::Timeout { pin: 37, value: 1 }.exit_code(),
+            EXIT_TIMEOUT
+        );
+        assert_eq!(
+            Error::UnknownPin {
+                name: String::from("x")
+            }
+            .exit_code(),
+            EXIT_UNKNOWN_PIN
+        );
+        let duplicate = Error::ConfigFile {
+            path: PathBuf::from("/etc/gpio.d/leds.toml"),
+            source: Box::new(Error::DuplicateName {
+                name: String::from("x"),
+                pin: 37,
+                other_pin: 73,
+            }),
+        };
+        assert_eq!(duplicate.exit_code(), EXIT_CONFIG);
+        let interrupted = Error::Interrupted {
+            signal: Signal::SIGINT,
+        };
+        assert_eq!(interrupted.exit_code(), 130);
+        assert_eq!(interrupted.to_string(), "interrupted by SIGINT");
+        assert_eq!(
+            duplicate.to_string(),
+            "/etc/gpio.d/leds.toml: pins 37 and 73 share duplicate name 'x'"
+        );
+    }
+}
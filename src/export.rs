@@ -6,23 +6,394 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::config::PinConfig;
+use crate::config::{GpioConfig, PinConfig};
 use crate::error::*;
 use lazy_static::lazy_static;
-use nix::unistd::{chown, Gid, Uid};
+use nix::errno::Errno;
+use nix::unistd::{access, chown, AccessFlags, Gid, Uid};
+use std::fmt;
 use std::fs;
 use std::io::ErrorKind;
 use std::os::unix::fs as unix_fs;
 use std::os::unix::fs::PermissionsExt;
 use std::path;
 use std::sync::Mutex;
-use sysfs_gpio;
+use std::thread;
+use std::time::{Duration, Instant};
+use sysfs_gpio::{self, Direction, Edge, Pin};
 use uzers::{Groups, Users, UsersCache};
 
 lazy_static! {
     static ref USERS_CACHE: Mutex<UsersCache> = Mutex::new(UsersCache::new());
 }
 
+/// Path of the sysfs directory of an exported pin
+pub fn sysfs_path(num: u64) -> path::PathBuf {
+    path::PathBuf::from(format!("/sys/class/gpio/gpio{}", num))
+}
+
+/// Attributes that must be writable before a pin is configured, and whether
+/// they must exist
+///
+/// The kernel omits `direction` for lines with a fixed direction and `edge`
+/// for lines which cannot interrupt.
+const READY_ATTRIBUTES: &[(&str, bool)] = &[("value", true), ("direction", false), ("edge", false)];
+
+/// Shortest delay between readiness checks, so a zero backoff does not spin
+const MIN_READY_BACKOFF: Duration = Duration::from_millis(1);
+
+/// How long (and how often) to wait for a freshly exported pin to be usable
+///
+/// After a pin is exported, the kernel creates its attribute files but udev
+/// may still be applying ownership and permissions to them.  Readiness is
+/// checked repeatedly, with the delay between attempts starting at
+/// `initial_backoff` (at least 1ms) and doubling up to `max_backoff`, until
+/// `timeout` has elapsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadyWait {
+    pub timeout: Duration,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for ReadyWait {
+    fn default() -> Self {
+        ReadyWait {
+            timeout: Duration::from_millis(1000),
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Wait for the attribute files of an exported pin to become usable
+///
+/// `value` in `/sys/class/gpio/gpio<num>` must exist and be writable by the
+/// current process, as must `direction` and `edge` if the kernel provides
+/// them (they are created along with `value`).  If an attribute does not
+/// become ready within the configured timeout, an `Error::NotReady` naming
+/// that attribute is returned.
+pub fn wait_until_ready(num: u64, wait: &ReadyWait) -> Result<()> {
+    let start = Instant::now();
+    let mut backoff = wait.initial_backoff;
+    for &(attribute, required) in READY_ATTRIBUTES {
+        let path = sysfs_path(num).join(attribute);
+        loop {
+            match access(&path, AccessFlags::W_OK) {
+                Ok(()) => break,
+                Err(Errno::ENOENT) if !required => break,
+                Err(e) => {
+                    let elapsed = start.elapsed();
+                    if elapsed >= wait.timeout {
+                        return Err(Error::NotReady {
+                            pin: num,
+                            attribute,
+                            timeout: wait.timeout,
+                            source: e,
+                        });
+                    }
+                    let delay = backoff.max(MIN_READY_BACKOFF);
+                    thread::sleep(delay.min(wait.timeout - elapsed));
+                    backoff = backoff.saturating_mul(2).min(wait.max_backoff);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A single action performed while exporting or unexporting a pin
+///
+/// Rather than acting inline, `export` and `unexport` first describe what
+/// they are going to do as a list of operations (see `export_operations`
+/// and `unexport_operations`) which are then performed in order.  The
+/// `Display` implementation describes each operation in terms of the
+/// equivalent shell command, which is used for dry runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    /// Export the pin (via /sys/class/gpio/export)
+    Export {
+        pin: u64,
+    },
+    /// Wait for the attribute files of the pin (see `wait_until_ready`)
+    WaitReady {
+        pin: u64,
+        ready: ReadyWait,
+    },
+    /// Unexport the pin (via /sys/class/gpio/unexport)
+    Unexport {
+        pin: u64,
+    },
+    /// Change user and group of the files in the gpio directory of the pin
+    Chown {
+        pin: u64,
+        uid: Option<Uid>,
+        gid: Option<Gid>,
+    },
+    /// Change the mode of the files in the gpio directory of the pin
+    Chmod {
+        pin: u64,
+        mode: u32,
+    },
+    SetActiveLow {
+        pin: u64,
+        active_low: bool,
+    },
+    SetDirection {
+        pin: u64,
+        direction: Direction,
+    },
+    SetValue {
+        pin: u64,
+        value: u8,
+    },
+    SetEdge {
+        pin: u64,
+        edge: Edge,
+    },
+    /// Create a directory (and its parents) if it does not exist yet
+    CreateDir {
+        path: path::PathBuf,
+    },
+    /// Create a symlink, atomically replacing an existing symlink
+    Symlink {
+        target: path::PathBuf,
+        path: path::PathBuf,
+    },
+    RemoveSymlink {
+        path: path::PathBuf,
+    },
+    /// Wait for some time (e.g. the `delay_ms` of a pin)
+    Delay {
+        duration: Duration,
+    },
+}
+
+/// Name of an edge as used in the sysfs `edge` attribute
+pub fn edge_str(edge: Edge) -> &'static str {
+    match edge {
+        Edge::NoInterrupt => "none",
+        Edge::RisingEdge => "rising",
+        Edge::FallingEdge => "falling",
+        Edge::BothEdges => "both",
+    }
+}
+
+/// Name of a direction as used in the sysfs `direction` attribute
+pub fn direction_str(direction: Direction) -> &'static str {
+    match direction {
+        Direction::In => "in",
+        Direction::Out => "out",
+        Direction::High => "high",
+        Direction::Low => "low",
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operation::Export { pin } => write!(f, "echo {} > /sys/class/gpio/export", pin),
+            Operation::WaitReady { pin, ready } => write!(
+                f,
+                "wait up to {:?} for {}/{{{}}}",
+                ready.timeout,
+                sysfs_path(pin).display(),
+                READY_ATTRIBUTES
+                    .iter()
+                    .map(|&(attribute, _)| attribute)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Operation::Unexport { pin } => write!(f, "echo {} > /sys/class/gpio/unexport", pin),
+            Operation::Chown { pin, uid, gid } => {
+                write!(f, "chown ")?;
+                if let Some(uid) = uid {
+                    write!(f, "{}", uid)?;
+                }
+                if let Some(gid) = gid {
+                    write!(f, ":{}", gid)?;
+                }
+                write!(f, " {}/*", sysfs_path(pin).display())
+            }
+            Operation::Chmod { pin, mode } => {
+                write!(f, "chmod {:o} {}/*", mode, sysfs_path(pin).display())
+            }
+            Operation::SetActiveLow { pin, active_low } => write!(
+                f,
+                "echo {} > {}/active_low",
+                u8::from(active_low),
+                sysfs_path(pin).display()
+            ),
+            Operation::SetDirection { pin, direction } => write!(
+                f,
+                "echo {} > {}/direction",
+                direction_str(direction),
+                sysfs_path(pin).display()
+            ),
+            Operation::SetValue { pin, value } => {
+                write!(f, "echo {} > {}/value", value, sysfs_path(pin).display())
+            }
+            Operation::SetEdge { pin, edge } => write!(
+                f,
+                "echo {} > {}/edge",
+                edge_str(edge),
+                sysfs_path(pin).display()
+            ),
+            Operation::CreateDir { ref path } => write!(f, "mkdir -p {}", path.display()),
+            Operation::Symlink {
+                ref target,
+                ref path,
+            } => write!(f, "ln -sfn {} {}", target.display(), path.display()),
+            Operation::RemoveSymlink { ref path } => write!(f, "rm {}", path.display()),
+            Operation::Delay { duration } => write!(f, "sleep {}", duration.as_secs_f64()),
+        }
+    }
+}
+
+impl Operation {
+    /// The pin and a description of the operation for error messages
+    ///
+    /// `None` for operations which are not about a single pin, or which
+    /// report the pin themselves.
+    fn pin_operation(&self) -> Option<(u64, &'static str)> {
+        match *self {
+            Operation::Export { pin } => Some((pin, "export")),
+            Operation::Unexport { pin } => Some((pin, "unexport")),
+            Operation::Chown { pin, .. } => Some((pin, "change owner")),
+            Operation::Chmod { pin, .. } => Some((pin, "change mode")),
+            Operation::SetActiveLow { pin, .. } => Some((pin, "set active_low")),
+            Operation::SetDirection { pin, .. } => Some((pin, "set direction")),
+            Operation::SetValue { pin, .. } => Some((pin, "set value")),
+            Operation::SetEdge { pin, .. } => Some((pin, "set edge")),
+            Operation::WaitReady { .. }
+            | Operation::CreateDir { .. }
+            | Operation::Symlink { .. }
+            | Operation::RemoveSymlink { .. }
+            | Operation::Delay { .. } => None,
+        }
+    }
+
+    /// Perform the operation
+    ///
+    /// Errors of operations on a pin are returned as `Error::PinOperation`.
+    pub fn run(&self) -> Result<()> {
+        match (self.perform(), self.pin_operation()) {
+            (Err(e), Some((pin, operation))) => Err(Error::pin_operation(pin, operation, e)),
+            (result, _) => result,
+        }
+    }
+
+    fn perform(&self) -> Result<()> {
+        match *self {
+            Operation::Export { pin } => Ok(Pin::new(pin).export()?),
+            Operation::WaitReady { pin, ref ready } => wait_until_ready(pin, ready),
+            Operation::Unexport { pin } => unexport_pin(pin),
+            Operation::Chown { pin, uid, gid } => chown_pin(pin, uid, gid),
+            Operation::Chmod { pin, mode } => chmod_pin(pin, mode),
+            Operation::SetActiveLow { pin, active_low } => {
+                Ok(Pin::new(pin).set_active_low(active_low)?)
+            }
+            Operation::SetDirection { pin, direction } => {
+                Ok(Pin::new(pin).set_direction(direction)?)
+            }
+            Operation::SetValue { pin, value } => Ok(Pin::new(pin).set_value(value)?),
+            Operation::SetEdge { pin, edge } => Ok(Pin::new(pin).set_edge(edge)?),
+            Operation::CreateDir { ref path } => Ok(fs::create_dir_all(path)?),
+            Operation::Symlink {
+                ref target,
+                ref path,
+            } => match unix_fs::symlink(target, path) {
+                Err(ref e) if e.kind() == ErrorKind::AlreadyExists => {
+                    // never replace anything but a symlink
+                    if !fs::symlink_metadata(path)?.file_type().is_symlink() {
+                        return Err(Error::NotASymlink { path: path.clone() });
+                    }
+                    replace_symlink(target, path)
+                }
+                Err(e) => Err(e.into()),
+                Ok(_) => Ok(()),
+            },
+            Operation::RemoveSymlink { ref path } => match fs::remove_file(path) {
+                Ok(_) => Ok(()),
+                Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e.into()),
+            },
+            Operation::Delay { duration } => {
+                thread::sleep(duration);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Perform the provided operations in order, stopping at the first error
+pub fn run(operations: &[Operation]) -> Result<()> {
+    for operation in operations {
+        operation.run()?;
+    }
+
+    Ok(())
+}
+
+/// Runs operations while recording how to undo them
+///
+/// Only the effects that leave traces behind are undone: pins exported by
+/// the transaction are unexported again and symlinks are restored to their
+/// previous state.  Settings applied to pins which were already exported
+/// are kept.
+#[derive(Debug, Default)]
+pub struct Transaction {
+    undo: Vec<Operation>,
+}
+
+impl Transaction {
+    pub fn new() -> Transaction {
+        Default::default()
+    }
+
+    /// Perform the provided operations in order, stopping at the first error
+    ///
+    /// Operations performed before the error are still recorded and will be
+    /// undone by `rollback`.
+    pub fn run(&mut self, operations: &[Operation]) -> Result<()> {
+        for operation in operations {
+            let undo = match *operation {
+                Operation::Export { pin } => Some(Operation::Unexport { pin }),
+                Operation::Symlink { ref path, .. } => match fs::read_link(path) {
+                    Ok(previous) => Some(Operation::Symlink {
+                        target: previous,
+                        path: path.clone(),
+                    }),
+                    Err(_) => Some(Operation::RemoveSymlink { path: path.clone() }),
+                },
+                _ => None,
+            };
+            operation.run()?;
+            self.undo.extend(undo);
+        }
+
+        Ok(())
+    }
+
+    /// Undo the recorded operations in reverse order
+    ///
+    /// All undo operations are attempted even if some of them fail; the
+    /// first error encountered is returned.
+    pub fn rollback(self) -> Result<()> {
+        let mut result = Ok(());
+        for operation in self.undo.iter().rev() {
+            if let Err(e) = operation.run() {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+
+        result
+    }
+}
+
 /// Unexport the pin specified in the provided config
 ///
 /// Unexporting a config (in this context) involves a few different
@@ -35,24 +406,229 @@ lazy_static! {
 /// If the GPIO was already unexported, this function will continue
 /// without an error as the desired end state is achieved.
 pub fn unexport(pin_config: &PinConfig, symlink_root: Option<&str>) -> Result<()> {
+    run(&unexport_operations(pin_config, symlink_root)?)
+}
+
+/// Describe the operations performed by `unexport`
+pub fn unexport_operations(
+    pin_config: &PinConfig,
+    symlink_root: Option<&str>,
+) -> Result<Vec<Operation>> {
+    let mut operations = Vec::new();
     if let Some(symroot) = symlink_root {
-        // create symlink for each name
-        for name in &pin_config.names {
-            let mut dst = path::PathBuf::from(symroot);
-            dst.push(name);
-            match fs::remove_file(dst) {
-                Ok(_) => (),
-                Err(ref e) if e.kind() == ErrorKind::NotFound => (),
-                Err(e) => return Err(e.into()),
-            };
+        operations.extend(remove_symlink_operations(pin_config, symroot)?);
+    }
+    if pin_config.get_pin().is_exported() {
+        operations.extend(safe_state_operations(pin_config));
+        operations.push(Operation::Unexport {
+            pin: pin_config.num,
+        });
+    }
+
+    Ok(operations)
+}
+
+/// Guard temporarily exporting a pin
+///
+/// If the pin is not exported yet, it is exported and configured (see
+/// `configure`) and unexported again when the guard is dropped, unless `keep`
+/// is called.  This also happens if configuring the pin fails.  Pins which
+/// already are exported are left untouched.
+#[derive(Debug)]
+pub struct TemporaryExport {
+    pin: Option<u64>,
+}
+
+impl TemporaryExport {
+    /// Export and configure the pin if it is not exported yet
+    pub fn new(pin_config: &PinConfig, ready: &ReadyWait) -> Result<TemporaryExport> {
+        TemporaryExport::start(
+            pin_config.num,
+            &TemporaryExport::operations(pin_config, ready),
+        )
+    }
+
+    /// Export the pin if it is not exported yet, without configuring it
+    ///
+    /// The pin keeps the direction and polarity the kernel exports it with.
+    pub fn unconfigured(num: u64, ready: &ReadyWait) -> Result<TemporaryExport> {
+        let operations = if Pin::new(num).is_exported() {
+            Vec::new()
+        } else {
+            export_pin_operations(num, ready)
+        };
+        TemporaryExport::start(num, &operations)
+    }
+
+    fn start(num: u64, operations: &[Operation]) -> Result<TemporaryExport> {
+        if operations.is_empty() {
+            return Ok(TemporaryExport { pin: None });
         }
+
+        operations[0].run()?;
+        // from here on, dropping the guard unexports the pin again
+        let guard = TemporaryExport { pin: Some(num) };
+        run(&operations[1..])?;
+        Ok(guard)
     }
 
+    /// Describe the operations performed by `new`
+    pub fn operations(pin_config: &PinConfig, ready: &ReadyWait) -> Vec<Operation> {
+        if pin_config.get_pin().is_exported() {
+            return Vec::new();
+        }
+        let mut operations = export_pin_operations(pin_config.num, ready);
+        operations.extend(configure_operations(pin_config));
+        operations
+    }
+
+    /// Whether the pin has been exported by this guard
+    pub fn is_temporary(&self) -> bool {
+        self.pin.is_some()
+    }
+
+    /// Keep the pin exported after the guard is dropped
+    pub fn keep(mut self) {
+        self.pin = None;
+    }
+}
+
+impl Drop for TemporaryExport {
+    fn drop(&mut self) {
+        if let Some(pin) = self.pin {
+            let _ = unexport_pin(pin);
+        }
+    }
+}
+
+/// Whether a pin with edge `current` reports the edges `requested` needs
+///
+/// A pin interrupting on both edges reports rising and falling edges too,
+/// but the caller then has to skip the edges it is not interested in.
+pub fn edge_covers(current: Edge, requested: Edge) -> bool {
+    current == requested || (current == Edge::BothEdges && requested != Edge::NoInterrupt)
+}
+
+/// Guard temporarily changing the edge of an exported pin
+///
+/// The edge set before is restored when the guard is dropped, so that the
+/// `edge` configured for other processes polling the pin survives commands
+/// waiting for edges themselves.  If the current edge already covers the
+/// requested one (see `edge_covers`), it is left untouched.  Errors while
+/// restoring are ignored, as there is no way to report them from `drop`.
+#[derive(Debug)]
+pub struct EdgeGuard {
+    pin: u64,
+    restore: Option<Edge>,
+}
+
+impl EdgeGuard {
+    /// Make the pin report `edge` until the guard is dropped
+    pub fn new(num: u64, edge: Edge) -> Result<EdgeGuard> {
+        let pin = Pin::new(num);
+        let current = pin
+            .get_edge()
+            .map_err(|e| Error::pin_operation(num, "read edge", e))?;
+        if edge_covers(current, edge) {
+            return Ok(EdgeGuard {
+                pin: num,
+                restore: None,
+            });
+        }
+        pin.set_edge(edge)
+            .map_err(|e| Error::pin_operation(num, "set edge", e))?;
+        Ok(EdgeGuard {
+            pin: num,
+            restore: Some(current),
+        })
+    }
+}
+
+impl Drop for EdgeGuard {
+    fn drop(&mut self) {
+        if let Some(edge) = self.restore {
+            let _ = Pin::new(self.pin).set_edge(edge);
+        }
+    }
+}
+
+/// Drive the pin to its `safe_value`
+///
+/// Only pins which have a `safe_value` configured and are currently exported
+/// as outputs are touched.
+pub fn safe_state(pin_config: &PinConfig) -> Result<()> {
+    run(&safe_state_operations(pin_config))
+}
+
+/// Describe the operations performed by `safe_state`
+pub fn safe_state_operations(pin_config: &PinConfig) -> Vec<Operation> {
+    let value = match pin_config.safe_value {
+        Some(value) => value,
+        None => return Vec::new(),
+    };
+    match pin_config.get_pin().get_direction() {
+        Ok(Direction::Out) => vec![Operation::SetValue {
+            pin: pin_config.num,
+            value,
+        }],
+        _ => Vec::new(),
+    }
+}
+
+/// Guard driving pins to their safe values when dropped
+///
+/// The safe values are also restored if the guard is dropped while unwinding
+/// from a panic (this does not happen with `panic = "abort"`).  Errors are
+/// ignored, as there is no way to report them from `drop`; each pin is
+/// attempted regardless of failures on the others.
+///
+/// ```no_run
+/// # use gpio_utils::config::GpioConfig;
+/// # use gpio_utils::export::SafeStateGuard;
+/// # let config = GpioConfig::load(&[]).unwrap();
+/// let _guard = SafeStateGuard::new(&config);
+/// // drive outputs; they are returned to their safe values when the guard
+/// // goes out of scope
+/// ```
+#[derive(Debug)]
+pub struct SafeStateGuard {
+    pins: Vec<PinConfig>,
+}
+
+impl SafeStateGuard {
+    /// Guard all configured pins which have a `safe_value`
+    pub fn new(config: &GpioConfig) -> SafeStateGuard {
+        SafeStateGuard {
+            pins: config
+                .get_pins()
+                .iter()
+                .filter(|p| p.safe_value.is_some())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Drop the guard without touching any pin
+    pub fn disarm(mut self) {
+        self.pins.clear();
+    }
+}
+
+impl Drop for SafeStateGuard {
+    fn drop(&mut self) {
+        for pin_config in &self.pins {
+            let _ = safe_state(pin_config);
+        }
+    }
+}
+
+/// Unexport the GPIO pin itself (via /sys/class/gpio/unexport)
+pub fn unexport_pin(num: u64) -> Result<()> {
     // unexport the pin itself.  On many boards, it turns out, some pins are
     // exported by the kernel itself but we might still be assigning names.  In
     // those cases we will get an error here.  We handle that rather than
     // exposing the error up the chain. (EINVAL)
-    let pin = pin_config.get_pin();
+    let pin = Pin::new(num);
     match pin.unexport() {
         Ok(_) => Ok(()),
         Err(sysfs_gpio::Error::Io(ref e)) if e.kind() == ErrorKind::InvalidInput => Ok(()),
@@ -60,21 +636,127 @@ pub fn unexport(pin_config: &PinConfig, symlink_root: Option<&str>) -> Result<()
     }
 }
 
+/// Remove the symlink in `symlink_root` for each name of the pin
+///
+/// Only symlinks pointing to this pin are removed; missing entries, symlinks
+/// to other pins and anything that is not a symlink are left untouched.
+pub fn remove_symlinks(pin_config: &PinConfig, symlink_root: &str) -> Result<()> {
+    run(&remove_symlink_operations(pin_config, symlink_root)?)
+}
+
+/// Describe the operations performed by `remove_symlinks`
+pub fn remove_symlink_operations(
+    pin_config: &PinConfig,
+    symlink_root: &str,
+) -> Result<Vec<Operation>> {
+    let target = sysfs_path(pin_config.num);
+    let mut operations = Vec::new();
+    for name in &pin_config.names {
+        let mut dst = path::PathBuf::from(symlink_root);
+        dst.push(name);
+        match fs::read_link(&dst) {
+            Ok(ref current) if *current == target => {
+                operations.push(Operation::RemoveSymlink { path: dst })
+            }
+            Ok(_) => (),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => (),
+            // not a symlink
+            Err(ref e) if e.kind() == ErrorKind::InvalidInput => (),
+            Err(e) => return Err(e.into()),
+        };
+    }
+
+    Ok(operations)
+}
+
+/// Options controlling the steps taken by `export` after exporting a pin
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions<'a> {
+    /// Root directory for the name symlinks, or `None` to skip creating them
+    pub symlink_root: Option<&'a str>,
+    /// How long to wait for the exported attribute files to become usable
+    pub ready: ReadyWait,
+}
+
 /// Export the pin specified in the provided config
 ///
 /// Exporting a pin (in this context) involves, a few different
 /// actions:
 ///
 /// 1. The GPIO pin itself is exported (via /sys/class/gpio/export)
-/// 2. For each GPIO name/alias, a symlink is created from
-///    `/var/run/gpio/<name>` -> `/sys/class/gpio<num>`.
+/// 2. We wait for the pin's attribute files to become usable (see
+///    `wait_until_ready`)
+/// 3. The configured user, group and mode are applied (see `set_ownership`)
+/// 4. The configured active low state, direction and edge are applied (see
+///    `configure`)
+/// 5. If a `symlink_root` is provided, a symlink is created for each
+///    GPIO name/alias from `/var/run/gpio/<name>` -> `/sys/class/gpio<num>`
+///    (see `create_symlinks`).
 ///
 /// If the GPIO is already exported, this function will continue
 /// without an error as the desired end state is achieved.
-pub fn export(pin_config: &PinConfig, symlink_root: Option<&str>) -> Result<()> {
-    let pin = pin_config.get_pin();
-    pin.export()?;
+pub fn export(pin_config: &PinConfig, opts: &ExportOptions) -> Result<()> {
+    run(&export_operations(pin_config, opts)?)
+}
+
+/// Describe the operations performed by `export`
+pub fn export_operations(pin_config: &PinConfig, opts: &ExportOptions) -> Result<Vec<Operation>> {
+    let mut operations = export_pin_operations(pin_config.num, &opts.ready);
+    operations.extend(ownership_operations(pin_config)?);
+    operations.extend(configure_operations(pin_config));
+    if let Some(symroot) = opts.symlink_root {
+        operations.extend(symlink_operations(pin_config, symroot)?);
+    }
+
+    Ok(operations)
+}
 
+/// Describe exporting a pin (unless it is exported) and waiting for it
+///
+/// These are the first steps of `export`; the pin is not configured.
+pub fn export_pin_operations(num: u64, ready: &ReadyWait) -> Vec<Operation> {
+    let mut operations = Vec::new();
+    if !Pin::new(num).is_exported() {
+        operations.push(Operation::Export { pin: num });
+    }
+    operations.push(Operation::WaitReady {
+        pin: num,
+        ready: *ready,
+    });
+    operations
+}
+
+/// Apply the configured user, group and mode to an exported pin
+///
+/// The settings are applied to each file in `/sys/class/gpio/gpio<num>`.
+/// Nothing is done if none of them are configured.
+pub fn set_ownership(pin_config: &PinConfig) -> Result<()> {
+    run(&ownership_operations(pin_config)?)
+}
+
+/// Describe the operations performed by `set_ownership`
+pub fn ownership_operations(pin_config: &PinConfig) -> Result<Vec<Operation>> {
+    let (uid, gid) = resolve_owner(pin_config)?;
+    let mut operations = Vec::new();
+    if uid.is_some() || gid.is_some() {
+        operations.push(Operation::Chown {
+            pin: pin_config.num,
+            uid,
+            gid,
+        });
+    }
+    if let Some(mode) = pin_config.mode {
+        operations.push(Operation::Chmod {
+            pin: pin_config.num,
+            mode,
+        });
+    }
+
+    Ok(operations)
+}
+
+/// Look up the uid and gid for the configured user and group of a pin
+pub fn resolve_owner(pin_config: &PinConfig) -> Result<(Option<Uid>, Option<Gid>)> {
     let uid = if let Some(username) = pin_config.user.as_ref() {
         Some(
             USERS_CACHE
@@ -101,48 +783,514 @@ pub fn export(pin_config: &PinConfig, symlink_root: Option<&str>) -> Result<()>
         None
     };
 
-    // change user, group, mode for files in gpio directory
-    if uid.is_some() || gid.is_some() || pin_config.mode.is_some() {
-        for entry in fs::read_dir(format!("/sys/class/gpio/gpio{}", &pin_config.num))? {
-            let e = entry?;
-            let metadata = e.metadata()?;
+    Ok((uid, gid))
+}
 
-            if metadata.is_file() {
-                if uid.is_some() || gid.is_some() {
-                    chown(e.path().as_path(), uid, gid)?;
-                }
+/// Change user and group of the files in the gpio directory of a pin
+pub fn chown_pin(num: u64, uid: Option<Uid>, gid: Option<Gid>) -> Result<()> {
+    for entry in fs::read_dir(sysfs_path(num))? {
+        let e = entry?;
+        if e.metadata()?.is_file() {
+            chown(e.path().as_path(), uid, gid)?;
+        }
+    }
 
-                if let Some(mode) = pin_config.mode {
-                    let mut permissions = metadata.permissions();
-                    permissions.set_mode(mode);
-                    fs::set_permissions(e.path().as_path(), permissions)?;
-                }
+    Ok(())
+}
+
+/// Change the mode of the files in the gpio directory of a pin
+pub fn chmod_pin(num: u64, mode: u32) -> Result<()> {
+    for entry in fs::read_dir(sysfs_path(num))? {
+        let e = entry?;
+        let metadata = e.metadata()?;
+        if metadata.is_file() {
+            let mut permissions = metadata.permissions();
+            permissions.set_mode(mode);
+            fs::set_permissions(e.path().as_path(), permissions)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply the configured active low state, direction and edge to an exported pin
+pub fn configure(pin_config: &PinConfig) -> Result<()> {
+    run(&configure_operations(pin_config))
+}
+
+/// Describe the operations performed by `configure`
+///
+/// Pins which already are outputs keep driving their current value if the
+/// configured direction is an output as well (see `drive_operations`).
+pub fn configure_operations(pin_config: &PinConfig) -> Vec<Operation> {
+    let mut operations = vec![Operation::SetActiveLow {
+        pin: pin_config.num,
+        active_low: pin_config.active_low,
+    }];
+    let is_output = pin_config.get_pin().get_direction().ok() == Some(Direction::Out);
+    if !is_output || pin_config.get_direction() == Direction::In {
+        operations.push(Operation::SetDirection {
+            pin: pin_config.num,
+            direction: initial_direction(pin_config),
+        });
+    }
+    // set after the direction, as the kernel only allows edges on inputs
+    if let Some(edge) = pin_config.edge {
+        operations.push(Operation::SetEdge {
+            pin: pin_config.num,
+            edge,
+        });
+    }
+    operations
+}
+
+/// Raw direction driving `value` on a pin with the provided polarity
+///
+/// The kernel applies `high` and `low` to the raw line, ignoring
+/// `active_low`, so the level is inverted for active low pins.
+pub fn output_direction(value: u8, active_low: bool) -> Direction {
+    if (value != 0) != active_low {
+        Direction::High
+    } else {
+        Direction::Low
+    }
+}
+
+/// Direction to write when turning the pin into its configured direction
+///
+/// Outputs with an `initial_value` are configured as `high` or `low` so that
+/// the pin never drives anything else.
+pub fn initial_direction(pin_config: &PinConfig) -> Direction {
+    match (pin_config.get_direction(), pin_config.initial_value) {
+        (Direction::Out, Some(value)) => output_direction(value, pin_config.active_low),
+        (direction, _) => direction,
+    }
+}
+
+/// Check that the configuration allows writing to the pin
+///
+/// Returns an `Error::NotWritable` for pins explicitly configured as inputs
+/// or with `writable = false`.  Pins without a configured direction are
+/// writable, even though they are exported as inputs.
+pub fn check_writable(pin_config: &PinConfig) -> Result<()> {
+    let reason = if pin_config.writable == Some(false) {
+        "configured as read-only (writable = false)"
+    } else if pin_config.direction == Some(Direction::In) {
+        "configured as an input"
+    } else {
+        return Ok(());
+    };
+    Err(Error::NotWritable {
+        pin: pin_config.num,
+        reason,
+    })
+}
+
+/// Describe the operations driving `value` on a pin without glitches
+///
+/// Writing `out` to the `direction` attribute drives the pin low until the
+/// value is written.  Pins which are not outputs yet are instead switched
+/// with a single `high` or `low` write, which sets direction and value at
+/// once.  Only the value is written for pins which already are outputs.
+pub fn drive_operations(num: u64, value: u8, active_low: bool) -> Vec<Operation> {
+    match Pin::new(num).get_direction() {
+        Ok(Direction::Out) => vec![Operation::SetValue { pin: num, value }],
+        _ => vec![Operation::SetDirection {
+            pin: num,
+            direction: output_direction(value, active_low),
+        }],
+    }
+}
+
+/// Create a symlink in `symlink_root` for each name of the pin
+///
+/// The symlink root directory is created if it does not exist yet.  Existing
+/// symlinks pointing somewhere other than this pin (e.g. because a name was
+/// moved to a different pin in the config) are atomically replaced.  Entries
+/// that are not symlinks are never replaced; an `Error::NotASymlink` is
+/// returned instead.
+pub fn create_symlinks(pin_config: &PinConfig, symlink_root: &str) -> Result<()> {
+    run(&symlink_operations(pin_config, symlink_root)?)
+}
+
+/// Describe the operations performed by `create_symlinks`
+pub fn symlink_operations(pin_config: &PinConfig, symlink_root: &str) -> Result<Vec<Operation>> {
+    // create root directory if not exists
+    let mut operations = vec![Operation::CreateDir {
+        path: path::PathBuf::from(symlink_root),
+    }];
+
+    // create symlink for each name
+    let target = sysfs_path(pin_config.num);
+    for name in &pin_config.names {
+        let mut dst = path::PathBuf::from(symlink_root);
+        dst.push(name);
+        match fs::read_link(&dst) {
+            Ok(ref current) if *current == target => continue,
+            Ok(_) => (),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => (),
+            Err(ref e) if e.kind() == ErrorKind::InvalidInput => {
+                return Err(Error::NotASymlink { path: dst });
             }
+            Err(e) => return Err(e.into()),
+        };
+        operations.push(Operation::Symlink {
+            target: target.clone(),
+            path: dst,
+        });
+    }
+
+    Ok(operations)
+}
+
+/// Atomically replace the symlink at `dst` by one pointing to `target`
+///
+/// The new symlink is created next to `dst` and renamed over it so that
+/// there is no point in time at which `dst` is missing.
+pub fn replace_symlink(target: &path::Path, dst: &path::Path) -> Result<()> {
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(dst.file_name().unwrap_or_default());
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp = dst.with_file_name(tmp_name);
+
+    match fs::remove_file(&tmp) {
+        Ok(_) => (),
+        Err(ref e) if e.kind() == ErrorKind::NotFound => (),
+        Err(e) => return Err(e.into()),
+    };
+    unix_fs::symlink(target, &tmp)?;
+    if let Err(e) = fs::rename(&tmp, dst) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+
+    Ok(())
+}
+
+/// Why a symlink in the symlink root is considered stale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaleReason {
+    /// The name is not assigned to any pin in the config
+    Orphaned,
+    /// The symlink points to a GPIO which is not exported
+    Dangling,
+}
+
+/// A stale symlink found in the symlink root by `find_stale_symlinks`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleSymlink {
+    pub path: path::PathBuf,
+    pub target: path::PathBuf,
+    pub reason: StaleReason,
+}
+
+/// Find stale symlinks in `symlink_root`
+///
+/// Only symlinks pointing to `/sys/class/gpio/gpio<num>` (i.e. symlinks as
+/// created by `create_symlinks`) are considered; any other entry in the
+/// symlink root is ignored.  A missing symlink root yields no stale symlinks.
+pub fn find_stale_symlinks(config: &GpioConfig, symlink_root: &str) -> Result<Vec<StaleSymlink>> {
+    let entries = match fs::read_dir(symlink_root) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut stale = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_symlink() {
+            continue;
         }
+        let target = fs::read_link(entry.path())?;
+        let is_gpio_link = target
+            .to_str()
+            .and_then(|t| t.strip_prefix("/sys/class/gpio/gpio"))
+            .is_some_and(|num| num.parse::<u64>().is_ok());
+        if !is_gpio_link {
+            continue;
+        }
+
+        let name = entry.file_name();
+        let configured = config
+            .get_pins()
+            .iter()
+            .any(|p| name.to_str().is_some_and(|n| p.names.contains(n)));
+        let reason = if !configured {
+            StaleReason::Orphaned
+        } else if fs::metadata(&target).is_err() {
+            StaleReason::Dangling
+        } else {
+            continue;
+        };
+        stale.push(StaleSymlink {
+            path: entry.path(),
+            target,
+            reason,
+        });
     }
+    stale.sort_by(|a, b| a.path.cmp(&b.path));
 
-    // if there is a symlink root provided, create symlink
-    if let Some(symroot) = symlink_root {
-        // create root directory if not exists
-        fs::create_dir_all(symroot)?;
-
-        // set active low
-        pin_config.get_pin().set_active_low(pin_config.active_low)?;
-
-        // set the pin direction
-        pin_config.get_pin().set_direction(pin_config.direction)?;
-
-        // create symlink for each name
-        for name in &pin_config.names {
-            let mut dst = path::PathBuf::from(symroot);
-            dst.push(name);
-            match unix_fs::symlink(format!("/sys/class/gpio/gpio{}", pin_config.num), dst) {
-                Err(ref e) if e.kind() == ErrorKind::AlreadyExists => (),
-                Err(e) => return Err(e.into()),
-                _ => (),
+    Ok(stale)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn pin_config(num: u64, names: &[&str]) -> PinConfig {
+        PinConfig {
+            names: names.iter().map(|n| n.to_string()).collect(),
+            ..PinConfig::new(num)
+        }
+    }
+
+    #[test]
+    fn test_create_symlinks_repairs_wrong_target() {
+        let tmp = TempDir::new("repair");
+        let root = tmp.path();
+        unix_fs::symlink(sysfs_path(37), root.join("status_led")).unwrap();
+
+        let pin = pin_config(38, &["status_led", "A28"]);
+        create_symlinks(&pin, root.to_str().unwrap()).unwrap();
+        assert_eq!(
+            fs::read_link(root.join("status_led")).unwrap(),
+            sysfs_path(38)
+        );
+        assert_eq!(fs::read_link(root.join("A28")).unwrap(), sysfs_path(38));
+        assert_eq!(fs::read_dir(root).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_create_symlinks_refuses_regular_file() {
+        let tmp = TempDir::new("regular");
+        let root = tmp.path();
+        fs::write(root.join("status_led"), "precious").unwrap();
+
+        let pin = pin_config(38, &["status_led"]);
+        match create_symlinks(&pin, root.to_str().unwrap()) {
+            Err(Error::NotASymlink { .. }) => (),
+            r => panic!("Expected NotASymlink Error, got {:?}", r),
+        }
+        assert_eq!(
+            fs::read_to_string(root.join("status_led")).unwrap(),
+            "precious"
+        );
+    }
+
+    #[test]
+    fn test_remove_symlinks_only_own_pin() {
+        let tmp = TempDir::new("remove");
+        let root = tmp.path();
+        unix_fs::symlink(sysfs_path(37), root.join("status_led")).unwrap();
+        unix_fs::symlink(sysfs_path(38), root.join("A28")).unwrap();
+
+        let pin = pin_config(38, &["status_led", "A28"]);
+        remove_symlinks(&pin, root.to_str().unwrap()).unwrap();
+        assert!(fs::symlink_metadata(root.join("status_led")).is_ok());
+        assert!(fs::symlink_metadata(root.join("A28")).is_err());
+    }
+
+    #[test]
+    fn test_output_direction() {
+        assert_eq!(output_direction(1, false), Direction::High);
+        assert_eq!(output_direction(0, false), Direction::Low);
+        assert_eq!(output_direction(1, true), Direction::Low);
+        assert_eq!(output_direction(0, true), Direction::High);
+    }
+
+    #[test]
+    fn test_edge_covers() {
+        assert!(edge_covers(Edge::RisingEdge, Edge::RisingEdge));
+        assert!(edge_covers(Edge::BothEdges, Edge::FallingEdge));
+        assert!(!edge_covers(Edge::BothEdges, Edge::NoInterrupt));
+        assert!(!edge_covers(Edge::RisingEdge, Edge::BothEdges));
+        assert!(!edge_covers(Edge::NoInterrupt, Edge::FallingEdge));
+    }
+
+    #[test]
+    fn test_wait_until_ready_times_out() {
+        // neither a zero nor a huge backoff may spin or overflow
+        for backoff in [Duration::ZERO, Duration::MAX] {
+            let wait = ReadyWait {
+                timeout: Duration::from_millis(5),
+                initial_backoff: backoff,
+                max_backoff: backoff,
             };
+            match wait_until_ready(999_999, &wait) {
+                Err(Error::NotReady {
+                    pin: 999_999,
+                    attribute: "value",
+                    ..
+                }) => (),
+                r => panic!("Expected NotReady Error, got {:?}", r),
+            }
         }
     }
 
-    Ok(())
+    #[test]
+    fn test_check_writable() {
+        let mut pin = pin_config(37, &["status_led"]);
+        // the direction defaults to in, but is not set explicitly
+        assert!(check_writable(&pin).is_ok());
+        pin.direction = Some(Direction::In);
+        assert!(matches!(
+            check_writable(&pin),
+            Err(Error::NotWritable { pin: 37, .. })
+        ));
+        pin.direction = Some(Direction::Out);
+        assert!(check_writable(&pin).is_ok());
+        pin.writable = Some(false);
+        assert!(check_writable(&pin).is_err());
+    }
+
+    #[test]
+    fn test_export_operations() {
+        let tmp = TempDir::new("export-ops");
+        let root = tmp.path();
+        unix_fs::symlink(sysfs_path(999_998), root.join("A27")).unwrap();
+        unix_fs::symlink(sysfs_path(999_999), root.join("green_led")).unwrap();
+
+        let mut pin = pin_config(999_999, &["status_led", "A27", "green_led"]);
+        pin.direction = Some(Direction::Out);
+        pin.mode = Some(0o664);
+        let opts = ExportOptions {
+            symlink_root: root.to_str(),
+            ready: ReadyWait::default(),
+        };
+        assert_eq!(
+            export_operations(&pin, &opts).unwrap(),
+            vec![
+                Operation::Export { pin: 999_999 },
+                Operation::WaitReady {
+                    pin: 999_999,
+                    ready: ReadyWait::default()
+                },
+                Operation::Chmod {
+                    pin: 999_999,
+                    mode: 0o664
+                },
+                Operation::SetActiveLow {
+                    pin: 999_999,
+                    active_low: false
+                },
+                Operation::SetDirection {
+                    pin: 999_999,
+                    direction: Direction::Out
+                },
+                Operation::CreateDir {
+                    path: root.to_path_buf()
+                },
+                Operation::Symlink {
+                    target: sysfs_path(999_999),
+                    path: root.join("A27")
+                },
+                Operation::Symlink {
+                    target: sysfs_path(999_999),
+                    path: root.join("status_led")
+                },
+            ]
+        );
+        assert_eq!(
+            Operation::Chmod {
+                pin: 999_999,
+                mode: 0o664
+            }
+            .to_string(),
+            "chmod 664 /sys/class/gpio/gpio999999/*"
+        );
+
+        // the pin is not exported, so only its own symlink is removed
+        assert_eq!(
+            unexport_operations(&pin, root.to_str()).unwrap(),
+            vec![Operation::RemoveSymlink {
+                path: root.join("green_led")
+            }]
+        );
+
+        // edges are configured after the direction
+        let mut button = pin_config(999_999, &["reset_button"]);
+        button.edge = Some(Edge::FallingEdge);
+        assert_eq!(
+            configure_operations(&button),
+            vec![
+                Operation::SetActiveLow {
+                    pin: 999_999,
+                    active_low: false
+                },
+                Operation::SetDirection {
+                    pin: 999_999,
+                    direction: Direction::In
+                },
+                Operation::SetEdge {
+                    pin: 999_999,
+                    edge: Edge::FallingEdge
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transaction_rollback_restores_symlinks() {
+        let tmp = TempDir::new("rollback");
+        let root = tmp.path();
+        unix_fs::symlink(sysfs_path(37), root.join("status_led")).unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction
+            .run(&[
+                Operation::Symlink {
+                    target: sysfs_path(38),
+                    path: root.join("status_led"),
+                },
+                Operation::Symlink {
+                    target: sysfs_path(38),
+                    path: root.join("A28"),
+                },
+            ])
+            .unwrap();
+        assert_eq!(
+            fs::read_link(root.join("status_led")).unwrap(),
+            sysfs_path(38)
+        );
+
+        transaction.rollback().unwrap();
+        assert_eq!(
+            fs::read_link(root.join("status_led")).unwrap(),
+            sysfs_path(37)
+        );
+        assert!(fs::symlink_metadata(root.join("A28")).is_err());
+    }
+
+    #[test]
+    fn test_find_stale_symlinks() {
+        let tmp = TempDir::new("stale");
+        let root = tmp.path();
+        unix_fs::symlink(sysfs_path(999_998), root.join("old_name")).unwrap();
+        unix_fs::symlink(sysfs_path(999_999), root.join("status_led")).unwrap();
+        unix_fs::symlink("/tmp", root.join("not_ours")).unwrap();
+        fs::write(root.join("regular"), "").unwrap();
+
+        let config = GpioConfig {
+            pins: vec![pin_config(999_999, &["status_led"])],
+            ..Default::default()
+        };
+        let stale = find_stale_symlinks(&config, root.to_str().unwrap()).unwrap();
+        assert_eq!(
+            stale,
+            vec![
+                StaleSymlink {
+                    path: root.join("old_name"),
+                    target: sysfs_path(999_998),
+                    reason: StaleReason::Orphaned,
+                },
+                StaleSymlink {
+                    path: root.join("status_led"),
+                    target: sysfs_path(999_999),
+                    reason: StaleReason::Dangling,
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,361 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::config::GpioConfig;
+use crate::error::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Boolean expression over the values of pins
+///
+/// Expressions consist of pin names, the values `0` and `1`, comparisons
+/// (`==`, `!=`), `!`, `&&`, `||` and parentheses, e.g.
+/// `door_closed == 1 && !(interlock || reset_button)`.  Pins are referred to
+/// like everywhere else, by name, number (other than `0` and `1`, which are
+/// values) or `<chip>:<line>`.  A pin on its own is true if it reads 1.
+/// `!` binds tightest, followed by the comparisons, `&&` and `||`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Pin(String),
+    Value(u8),
+    Not(Box<Expr>),
+    Eq(Box<Expr>, Box<Expr>),
+    Ne(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Names of the pins the expression refers to
+    pub fn pins(&self) -> BTreeSet<&str> {
+        let mut pins = BTreeSet::new();
+        self.collect_pins(&mut pins);
+        pins
+    }
+
+    fn collect_pins<'a>(&'a self, pins: &mut BTreeSet<&'a str>) {
+        match *self {
+            Expr::Pin(ref name) => {
+                pins.insert(name);
+            }
+            Expr::Value(_) => {}
+            Expr::Not(ref e) => e.collect_pins(pins),
+            Expr::Eq(ref a, ref b)
+            | Expr::Ne(ref a, ref b)
+            | Expr::And(ref a, ref b)
+            | Expr::Or(ref a, ref b) => {
+                a.collect_pins(pins);
+                b.collect_pins(pins);
+            }
+        }
+    }
+
+    /// Check that all pins the expression refers to can be resolved
+    ///
+    /// See `GpioConfig::resolve_pin` for the pins accepted.
+    pub fn validate(&self, config: &GpioConfig) -> Result<()> {
+        for name in self.pins() {
+            config.resolve_pin(name)?;
+        }
+        Ok(())
+    }
+
+    /// Evaluate the expression with the provided pin values
+    ///
+    /// Pins missing from `values` read as 0.
+    pub fn eval(&self, values: &BTreeMap<String, u8>) -> bool {
+        self.value(values) != 0
+    }
+
+    fn value(&self, values: &BTreeMap<String, u8>) -> u8 {
+        let truth = |b: bool| u8::from(b);
+        match *self {
+            Expr::Pin(ref name) => values.get(name).copied().unwrap_or(0),
+            Expr::Value(value) => value,
+            Expr::Not(ref e) => truth(!e.eval(values)),
+            Expr::Eq(ref a, ref b) => truth(a.value(values) == b.value(values)),
+            Expr::Ne(ref a, ref b) => truth(a.value(values) != b.value(values)),
+            Expr::And(ref a, ref b) => truth(a.eval(values) && b.eval(values)),
+            Expr::Or(ref a, ref b) => truth(a.eval(values) || b.eval(values)),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Expr::Pin(ref name) => write!(f, "{}", name),
+            Expr::Value(value) => write!(f, "{}", value),
+            Expr::Not(ref e) => write!(f, "!{}", e),
+            Expr::Eq(ref a, ref b) => write!(f, "({} == {})", a, b),
+            Expr::Ne(ref a, ref b) => write!(f, "({} != {})", a, b),
+            Expr::And(ref a, ref b) => write!(f, "({} && {})", a, b),
+            Expr::Or(ref a, ref b) => write!(f, "({} || {})", a, b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Name(String),
+    Value(u8),
+    Eq,
+    Ne,
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Name(ref name) => write!(f, "'{}'", name),
+            Token::Value(value) => write!(f, "'{}'", value),
+            Token::Eq => write!(f, "'=='"),
+            Token::Ne => write!(f, "'!='"),
+            Token::And => write!(f, "'&&'"),
+            Token::Or => write!(f, "'||'"),
+            Token::Not => write!(f, "'!'"),
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+        }
+    }
+}
+
+fn invalid(position: usize, reason: impl Into<String>) -> Error {
+    Error::InvalidExpression {
+        position,
+        reason: reason.into(),
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' || c == ':'
+}
+
+/// Split the expression into tokens and their (byte) positions
+fn tokenize(s: &str) -> Result<Vec<(usize, Token)>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some((pos, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '=' if chars.next_if(|&(_, c)| c == '=').is_some() => Token::Eq,
+            '!' if chars.next_if(|&(_, c)| c == '=').is_some() => Token::Ne,
+            '!' => Token::Not,
+            '&' if chars.next_if(|&(_, c)| c == '&').is_some() => Token::And,
+            '|' if chars.next_if(|&(_, c)| c == '|').is_some() => Token::Or,
+            c if is_name_char(c) => {
+                let mut end = pos + c.len_utf8();
+                while let Some((next, c)) = chars.next_if(|&(_, c)| is_name_char(c)) {
+                    end = next + c.len_utf8();
+                }
+                let word = &s[pos..end];
+                match word {
+                    "0" => Token::Value(0),
+                    "1" => Token::Value(1),
+                    // other numbers are pin numbers
+                    _ => Token::Name(word.to_string()),
+                }
+            }
+            c => return Err(invalid(pos, format!("unexpected '{}'", c))),
+        };
+        tokens.push((pos, token));
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the tokens, one method per precedence level
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |&(pos, _)| pos)
+    }
+
+    fn accept(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.accept(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.comparison()?;
+        while self.accept(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.comparison()?));
+        }
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let expr = self.unary()?;
+        if self.accept(&Token::Eq) {
+            Ok(Expr::Eq(Box::new(expr), Box::new(self.unary()?)))
+        } else if self.accept(&Token::Ne) {
+            Ok(Expr::Ne(Box::new(expr), Box::new(self.unary()?)))
+        } else {
+            Ok(expr)
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.accept(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        let position = self.position();
+        match self.tokens.get(self.pos).map(|(_, token)| token.clone()) {
+            Some(Token::Name(name)) => {
+                self.pos += 1;
+                Ok(Expr::Pin(name))
+            }
+            Some(Token::Value(value)) => {
+                self.pos += 1;
+                Ok(Expr::Value(value))
+            }
+            Some(Token::Open) => {
+                self.pos += 1;
+                let expr = self.or()?;
+                if !self.accept(&Token::Close) {
+                    return Err(invalid(self.position(), "expected ')'"));
+                }
+                Ok(expr)
+            }
+            Some(token) => Err(invalid(position, format!("unexpected {}", token))),
+            None => Err(invalid(position, "unexpected end of expression")),
+        }
+    }
+}
+
+impl FromStr for Expr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Expr> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+            end: s.len(),
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(invalid(parser.position(), format!("unexpected {}", token))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s: &str) -> Expr {
+        Expr::from_str(s).unwrap()
+    }
+
+    fn values(values: &[(&str, u8)]) -> BTreeMap<String, u8> {
+        values
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("door_closed == 1 && interlock == 0").to_string(),
+            "((door_closed == 1) && (interlock == 0))"
+        );
+        assert_eq!(parse("a || b && c").to_string(), "(a || (b && c))");
+        assert_eq!(parse("(a || b) && !c").to_string(), "((a || b) && !c)");
+        assert_eq!(parse("!a != b").to_string(), "(!a != b)");
+        assert_eq!(parse("!!(a)").to_string(), "!!a");
+        assert_eq!(
+            parse("a&&b").pins().into_iter().collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            parse("17 && !gpiochip1:13 == 0").to_string(),
+            "(17 && (!gpiochip1:13 == 0))"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (s, position) in [
+            ("", 0),
+            ("a &&", 4),
+            ("(a || b", 7),
+            ("a b", 2),
+            ("a = 1", 2),
+            ("a & b", 2),
+            ("a == ==", 5),
+            ("a)", 1),
+        ] {
+            match Expr::from_str(s) {
+                Err(Error::InvalidExpression { position: p, .. }) => {
+                    assert_eq!(p, position, "position for {:?}", s)
+                }
+                r => panic!("Expected InvalidExpression for {:?}, got {:?}", s, r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_eval() {
+        let expr = parse("door_closed == 1 && interlock == 0");
+        assert!(expr.eval(&values(&[("door_closed", 1), ("interlock", 0)])));
+        assert!(!expr.eval(&values(&[("door_closed", 1), ("interlock", 1)])));
+
+        let expr = parse("!(a || b) != c");
+        assert!(expr.eval(&values(&[("a", 0), ("b", 0), ("c", 0)])));
+        assert!(!expr.eval(&values(&[("a", 0), ("b", 0), ("c", 1)])));
+        assert!(expr.eval(&values(&[("a", 1), ("b", 0), ("c", 1)])));
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = GpioConfig::from_str(
+            r#"
+[[pins]]
+num = 73
+names = ["door_closed"]
+"#,
+        )
+        .unwrap();
+        assert!(parse("door_closed == 1").validate(&config).is_ok());
+        // unconfigured pins are accepted by number, like everywhere else
+        assert!(parse("door_closed && !27").validate(&config).is_ok());
+        match parse("door_closed && interlock").validate(&config) {
+            Err(Error::UnknownPin { ref name }) if name == "interlock" => (),
+            r => panic!("Expected UnknownPin Error, got {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::config::PinConfig;
+use std::cmp;
+use std::time::Duration;
+
+/// Settings of an `EdgeFilter`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FilterConfig {
+    /// Report a change only once the level has not changed for this long
+    /// (the change is reported at the end of the period)
+    pub debounce: Option<Duration>,
+    /// Drop pulses shorter than this (changes which last are reported with
+    /// the time of the edge which started them)
+    pub min_pulse: Option<Duration>,
+}
+
+impl FilterConfig {
+    /// Filter settings of a pin (`debounce_ms` and `min_pulse_ms`)
+    pub fn from_pin(pin_config: &PinConfig) -> FilterConfig {
+        FilterConfig {
+            debounce: pin_config.debounce_ms.map(Duration::from_millis),
+            min_pulse: pin_config.min_pulse_ms.map(Duration::from_millis),
+        }
+    }
+
+    /// Whether any filtering is configured
+    pub fn is_enabled(&self) -> bool {
+        self.debounce.is_some() || self.min_pulse.is_some()
+    }
+}
+
+/// A change of level let through by an `EdgeFilter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    pub value: u8,
+    /// Time to report the change at (on the clock the edges are fed with)
+    pub at: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Pending {
+    value: u8,
+    /// Time of the edge which changed the level to `value`
+    since: Duration,
+    /// Time of the most recent edge
+    last_edge: Duration,
+}
+
+/// Debounce and glitch filter for the edges of a single pin
+///
+/// This is a pure state machine: the caller feeds it the raw edges with
+/// `edge` and calls `poll` once `deadline` has passed.  Both return the
+/// changes to report.  Without any filtering configured, every edge is let
+/// through immediately.
+#[derive(Debug, Clone)]
+pub struct EdgeFilter {
+    config: FilterConfig,
+    reported: u8,
+    pending: Option<Pending>,
+}
+
+impl EdgeFilter {
+    /// Create a filter for a pin currently reading `value`
+    pub fn new(config: FilterConfig, value: u8) -> EdgeFilter {
+        EdgeFilter {
+            config,
+            reported: value,
+            pending: None,
+        }
+    }
+
+    /// Feed an edge after which the pin read `value` at time `at`
+    pub fn edge(&mut self, value: u8, at: Duration) -> Option<Change> {
+        if !self.config.is_enabled() {
+            self.reported = value;
+            return Some(Change { value, at });
+        }
+
+        if value == self.reported {
+            // the level went back before the change was confirmed
+            self.pending = None;
+            return None;
+        }
+        let since = match self.pending {
+            Some(pending) if pending.value == value => pending.since,
+            _ => at,
+        };
+        self.pending = Some(Pending {
+            value,
+            since,
+            last_edge: at,
+        });
+        None
+    }
+
+    /// Time at which a pending change is confirmed, if there is one
+    pub fn deadline(&self) -> Option<Duration> {
+        let pending = self.pending?;
+        let debounced = pending.last_edge + self.config.debounce.unwrap_or(Duration::ZERO);
+        let long_enough = pending.since + self.config.min_pulse.unwrap_or(Duration::ZERO);
+        Some(cmp::max(debounced, long_enough))
+    }
+
+    /// Confirm the pending change if its deadline has passed at time `now`
+    pub fn poll(&mut self, now: Duration) -> Option<Change> {
+        let deadline = self.deadline()?;
+        if now < deadline {
+            return None;
+        }
+        let pending = self.pending.take()?;
+        self.reported = pending.value;
+        let at = match self.config.debounce {
+            Some(_) => deadline,
+            None => pending.since,
+        };
+        Some(Change {
+            value: pending.value,
+            at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn filter(debounce: Option<u64>, min_pulse: Option<u64>) -> EdgeFilter {
+        let config = FilterConfig {
+            debounce: debounce.map(ms),
+            min_pulse: min_pulse.map(ms),
+        };
+        EdgeFilter::new(config, 0)
+    }
+
+    #[test]
+    fn test_unfiltered() {
+        let mut f = filter(None, None);
+        assert_eq!(
+            f.edge(1, ms(10)),
+            Some(Change {
+                value: 1,
+                at: ms(10)
+            })
+        );
+        assert_eq!(
+            f.edge(0, ms(11)),
+            Some(Change {
+                value: 0,
+                at: ms(11)
+            })
+        );
+        assert_eq!(f.deadline(), None);
+    }
+
+    #[test]
+    fn test_debounce() {
+        let mut f = filter(Some(20), None);
+        // a burst of bounces is reported once, after it settled
+        for (value, at) in [(1, 100), (0, 102), (1, 105), (0, 106), (1, 110)] {
+            assert_eq!(f.edge(value, ms(at)), None);
+        }
+        assert_eq!(f.deadline(), Some(ms(130)));
+        assert_eq!(f.poll(ms(129)), None);
+        assert_eq!(
+            f.poll(ms(131)),
+            Some(Change {
+                value: 1,
+                at: ms(130)
+            })
+        );
+        assert_eq!(f.deadline(), None);
+
+        // bouncing back to the reported level is no change at all
+        assert_eq!(f.edge(0, ms(200)), None);
+        assert_eq!(f.edge(1, ms(205)), None);
+        assert_eq!(f.deadline(), None);
+        assert_eq!(f.poll(ms(300)), None);
+    }
+
+    #[test]
+    fn test_min_pulse() {
+        let mut f = filter(None, Some(10));
+        // a glitch is dropped
+        assert_eq!(f.edge(1, ms(100)), None);
+        assert_eq!(f.edge(0, ms(105)), None);
+        assert_eq!(f.poll(ms(200)), None);
+
+        // repeated edges to the same level do not restart the pulse, and
+        // the change is reported with the time it started
+        assert_eq!(f.edge(1, ms(300)), None);
+        assert_eq!(f.edge(1, ms(308)), None);
+        assert_eq!(f.deadline(), Some(ms(310)));
+        assert_eq!(
+            f.poll(ms(310)),
+            Some(Change {
+                value: 1,
+                at: ms(300)
+            })
+        );
+    }
+
+    #[test]
+    fn test_debounce_and_min_pulse() {
+        let mut f = filter(Some(5), Some(20));
+        assert_eq!(f.edge(1, ms(100)), None);
+        assert_eq!(f.edge(1, ms(102)), None);
+        // the pulse must last 20ms even though it was stable after 5ms
+        assert_eq!(f.deadline(), Some(ms(120)));
+        assert_eq!(f.poll(ms(110)), None);
+        assert_eq!(f.edge(1, ms(118)), None);
+        // and it must have been stable for 5ms
+        assert_eq!(f.deadline(), Some(ms(123)));
+        assert_eq!(
+            f.poll(ms(125)),
+            Some(Change {
+                value: 1,
+                at: ms(123)
+            })
+        );
+    }
+}
@@ -0,0 +1,582 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::button::{Button, GestureEvent};
+use crate::config::{GpioConfig, PinConfig};
+use crate::error::*;
+use crate::export::{self, EdgeGuard, ExportOptions, Operation, TemporaryExport};
+use crate::expr::Expr;
+use crate::filter::FilterConfig;
+use crate::monitor::Monitor;
+use crate::sequence;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use sysfs_gpio::{Direction, Edge};
+
+/// How `Gpio::read`, `Gpio::write` and `Gpio::poll` access a pin
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccessOptions {
+    /// Keep the pin exported if it had to be exported temporarily
+    pub keep: bool,
+    /// Write even if the pin is configured as an input or not writable
+    pub force: bool,
+}
+
+/// Level of a pin to wait for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Value as read and written by the other commands, i.e. relative to
+    /// the polarity (`0`/`1`, `inactive`/`active`)
+    Logical(u8),
+    /// Electrical level, independent of the polarity (`low`/`high`)
+    Physical(u8),
+}
+
+impl Level {
+    /// The value the pin reads at this level with the provided polarity
+    pub fn value(self, active_low: bool) -> u8 {
+        match self {
+            Level::Logical(value) => value,
+            Level::Physical(level) if active_low => 1 - level,
+            Level::Physical(level) => level,
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Level> {
+        match s {
+            "0" | "inactive" => Ok(Level::Logical(0)),
+            "1" | "active" => Ok(Level::Logical(1)),
+            "low" => Ok(Level::Physical(0)),
+            "high" => Ok(Level::Physical(1)),
+            _ => Err(Error::Msg(format!(
+                "Invalid level '{}' (expected 0, 1, low, high, inactive or active)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Configured and live state of a pin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinStatus {
+    pub num: u64,
+    pub names: Vec<String>,
+    /// Whether the pin is exported by `gpio export-all`
+    pub export: bool,
+    /// Whether the pin is currently exported
+    pub exported: bool,
+    /// Configured direction
+    pub direction: Direction,
+    /// Configured polarity
+    pub active_low: bool,
+    /// Configured edge, `None` if it is left unchanged
+    pub edge: Option<Edge>,
+    /// Current value, `None` if the pin is not exported
+    pub value: Option<u8>,
+}
+
+/// Access to the GPIOs described by a configuration
+///
+/// This is what the `gpio` command is built on.  Nothing here prints or
+/// exits; failures are reported as `Error`s carrying the pin and operation.
+///
+/// ```no_run
+/// use gpio_utils::config::GpioConfig;
+/// use gpio_utils::gpio::{AccessOptions, Gpio};
+///
+/// # fn main() -> gpio_utils::error::Result<()> {
+/// let gpio = Gpio::new(GpioConfig::load(&[])?);
+/// let led = gpio.pin("status_led")?;
+/// gpio.write(&led, 1, &AccessOptions::default())?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Gpio {
+    config: GpioConfig,
+}
+
+impl Gpio {
+    pub fn new(config: GpioConfig) -> Gpio {
+        Gpio { config }
+    }
+
+    pub fn config(&self) -> &GpioConfig {
+        &self.config
+    }
+
+    /// Look up a pin by name, number or `<chip>:<line>`
+    ///
+    /// See `GpioConfig::resolve_pin` for how pins which are not configured
+    /// are handled.
+    pub fn pin(&self, name: &str) -> Result<Cow<'_, PinConfig>> {
+        self.config.resolve_pin(name)
+    }
+
+    /// Whether the pin is configured (rather than resolved ad-hoc)
+    fn is_configured(&self, pin_config: &PinConfig) -> bool {
+        self.config
+            .get_pins()
+            .iter()
+            .any(|p| p.num == pin_config.num)
+    }
+
+    /// Whether the pin is exported without touching its direction and
+    /// polarity
+    ///
+    /// This is the case for ad-hoc pins, unless a direction is given for
+    /// them (as `write` does), so that exporting a pin used by something
+    /// else (e.g. a live output) does not change it.
+    fn keeps_settings(&self, pin_config: &PinConfig) -> bool {
+        pin_config.direction.is_none() && !self.is_configured(pin_config)
+    }
+
+    /// Options for `export` using the configured symlink root
+    pub fn export_options(&self) -> ExportOptions<'_> {
+        ExportOptions {
+            symlink_root: Some(self.config.get_symlink_root()),
+            ready: self.config.get_ready_wait(),
+        }
+    }
+
+    /// Export and configure the pin and create its symlinks
+    pub fn export(&self, pin_config: &PinConfig, opts: &ExportOptions) -> Result<()> {
+        export::run(&self.export_operations(pin_config, opts)?)
+    }
+
+    /// Describe the operations performed by `export`
+    pub fn export_operations(
+        &self,
+        pin_config: &PinConfig,
+        opts: &ExportOptions,
+    ) -> Result<Vec<Operation>> {
+        if self.keeps_settings(pin_config) {
+            return Ok(export::export_pin_operations(pin_config.num, &opts.ready));
+        }
+        export::export_operations(pin_config, opts)
+    }
+
+    /// Unexport the pin (after driving its safe value) and remove its
+    /// symlinks from `symlink_root`
+    pub fn unexport(&self, pin_config: &PinConfig, symlink_root: Option<&str>) -> Result<()> {
+        export::run(&self.unexport_operations(pin_config, symlink_root)?)
+    }
+
+    /// Describe the operations performed by `unexport`
+    pub fn unexport_operations(
+        &self,
+        pin_config: &PinConfig,
+        symlink_root: Option<&str>,
+    ) -> Result<Vec<Operation>> {
+        export::unexport_operations(pin_config, symlink_root)
+    }
+
+    fn temporary_export(&self, pin_config: &PinConfig) -> Result<TemporaryExport> {
+        let ready = self.config.get_ready_wait();
+        if self.keeps_settings(pin_config) {
+            return TemporaryExport::unconfigured(pin_config.num, &ready);
+        }
+        TemporaryExport::new(pin_config, &ready)
+    }
+
+    /// Read the value of a pin, exporting it temporarily if needed
+    pub fn read(&self, pin_config: &PinConfig, opts: &AccessOptions) -> Result<u8> {
+        let guard = self.temporary_export(pin_config)?;
+        let result = pin_config
+            .get_pin()
+            .get_value()
+            .map_err(|e| Error::pin_operation(pin_config.num, "read value", e));
+        release(guard, opts);
+        result
+    }
+
+    /// Drive `value` on a pin, exporting it temporarily if needed
+    ///
+    /// Configured inputs and pins with `writable = false` are refused with
+    /// an `Error::NotWritable` unless `opts.force` is set.
+    pub fn write(&self, pin_config: &PinConfig, value: u8, opts: &AccessOptions) -> Result<()> {
+        self.check_writable(pin_config, opts)?;
+        let guard = self.temporary_export(&write_target(pin_config, value))?;
+        // a temporarily exported pin already drives the value
+        let result = if guard.is_temporary() {
+            Ok(())
+        } else {
+            export::run(&drive_operations(pin_config, value))
+        };
+        release(guard, opts);
+        result
+    }
+
+    /// Describe the operations performed by `write`
+    pub fn write_operations(
+        &self,
+        pin_config: &PinConfig,
+        value: u8,
+        opts: &AccessOptions,
+    ) -> Result<Vec<Operation>> {
+        self.check_writable(pin_config, opts)?;
+        let mut operations = TemporaryExport::operations(
+            &write_target(pin_config, value),
+            &self.config.get_ready_wait(),
+        );
+        if operations.is_empty() {
+            return Ok(drive_operations(pin_config, value));
+        }
+        if !opts.keep {
+            operations.push(Operation::Unexport {
+                pin: pin_config.num,
+            });
+        }
+        Ok(operations)
+    }
+
+    fn check_writable(&self, pin_config: &PinConfig, opts: &AccessOptions) -> Result<()> {
+        // ad-hoc pins have no configured direction to honor
+        if opts.force || !self.is_configured(pin_config) {
+            return Ok(());
+        }
+        export::check_writable(pin_config)
+    }
+
+    /// Wait for an edge on a pin, exporting it temporarily if needed
+    ///
+    /// Returns the new value, or `None` if there was no edge within
+    /// `timeout` (if any).  With `debounce_ms` or `min_pulse_ms` configured
+    /// for the pin, only edges passing the filter are reported.
+    pub fn poll(
+        &self,
+        pin_config: &PinConfig,
+        edge: Edge,
+        timeout: Option<Duration>,
+        opts: &AccessOptions,
+    ) -> Result<Option<u8>> {
+        let guard = self.temporary_export(pin_config)?;
+        let result = if FilterConfig::from_pin(pin_config).is_enabled() {
+            poll_filtered(pin_config, edge, timeout)
+        } else {
+            poll(pin_config, edge, timeout)
+        };
+        release(guard, opts);
+        result
+    }
+
+    /// Wait until a pin is at `level`, exporting it temporarily if needed
+    ///
+    /// Returns the value read once the level is reached, immediately if the
+    /// pin already is at the level.  Physical levels take the polarity of
+    /// the pin into account.  Fails with an `Error::Timeout` if the level is
+    /// not reached within `timeout` (if any).
+    pub fn wait(
+        &self,
+        pin_config: &PinConfig,
+        level: Level,
+        timeout: Option<Duration>,
+        opts: &AccessOptions,
+    ) -> Result<u8> {
+        let guard = self.temporary_export(pin_config)?;
+        let active_low = pin_config
+            .get_pin()
+            .get_active_low()
+            .unwrap_or(pin_config.active_low);
+        let value = level.value(active_low);
+        let result = sequence::wait_for_value(pin_config, value, timeout).map(|()| value);
+        release(guard, opts);
+        result
+    }
+
+    /// Wait until `expr` holds, exporting the pins it refers to if needed
+    ///
+    /// The expression is evaluated right away and again after every edge on
+    /// any of its pins.  Returns the values of the pins for which it held.
+    /// Fails with an `Error::ExprTimeout` if it does not hold within
+    /// `timeout` (if any).
+    pub fn wait_expr(
+        &self,
+        expr: &Expr,
+        timeout: Option<Duration>,
+        opts: &AccessOptions,
+    ) -> Result<BTreeMap<String, u8>> {
+        let pins = expr
+            .pins()
+            .into_iter()
+            .map(|name| Ok((name, self.pin(name)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut guards = Vec::new();
+        let result = (|| {
+            let mut monitor = Monitor::new()?;
+            for (name, pin_config) in &pins {
+                guards.push(self.temporary_export(pin_config)?);
+                monitor.add(pin_config, name)?;
+            }
+
+            let deadline = timeout.map(|t| Instant::now() + t);
+            loop {
+                let mut values = BTreeMap::new();
+                for (name, pin_config) in &pins {
+                    let value = pin_config
+                        .get_pin()
+                        .get_value()
+                        .map_err(|e| Error::pin_operation(pin_config.num, "read value", e))?;
+                    values.insert(name.to_string(), value);
+                }
+                if expr.eval(&values) {
+                    return Ok(values);
+                }
+                let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+                if remaining == Some(Duration::ZERO) {
+                    return Err(Error::ExprTimeout {
+                        expr: expr.to_string(),
+                    });
+                }
+                monitor.next_event(remaining)?;
+            }
+        })();
+        for guard in guards {
+            release(guard, opts);
+        }
+        result
+    }
+
+    /// Detect gestures on a button, exporting it temporarily if needed
+    ///
+    /// `on_gesture` is called with every gesture detected (see `Button`),
+    /// until it returns `false` or fails.  Events carry `name`.
+    pub fn button<F>(
+        &self,
+        pin_config: &PinConfig,
+        name: &str,
+        opts: &AccessOptions,
+        mut on_gesture: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&GestureEvent) -> Result<bool>,
+    {
+        let guard = self.temporary_export(pin_config)?;
+        let result = (|| {
+            let mut button = Button::new(pin_config, name)?;
+            loop {
+                if let Some(event) = button.next_gesture(None)? {
+                    if !on_gesture(&event)? {
+                        return Ok(());
+                    }
+                }
+            }
+        })();
+        release(guard, opts);
+        result
+    }
+
+    /// Report the configuration and live state of a pin
+    pub fn status(&self, pin_config: &PinConfig) -> Result<PinStatus> {
+        let pin = pin_config.get_pin();
+        let exported = pin.is_exported();
+        let value = if exported {
+            let value = pin
+                .get_value()
+                .map_err(|e| Error::pin_operation(pin_config.num, "read value", e))?;
+            Some(value)
+        } else {
+            None
+        };
+        Ok(PinStatus {
+            num: pin_config.num,
+            names: pin_config.names.iter().cloned().collect(),
+            export: pin_config.export,
+            exported,
+            direction: pin_config.get_direction(),
+            active_low: pin_config.active_low,
+            edge: pin_config.edge,
+            value,
+        })
+    }
+
+    /// Report the configuration and live state of all configured pins
+    pub fn statuses(&self) -> Result<Vec<PinStatus>> {
+        self.config
+            .get_pins()
+            .iter()
+            .map(|pin_config| self.status(pin_config))
+            .collect()
+    }
+}
+
+fn release(guard: TemporaryExport, opts: &AccessOptions) {
+    if opts.keep {
+        guard.keep();
+    }
+}
+
+/// Configuration a pin is temporarily exported with for writing `value`
+///
+/// The pin is turned into an output driving the value in one step.
+fn write_target(pin_config: &PinConfig, value: u8) -> PinConfig {
+    PinConfig {
+        direction: Some(Direction::Out),
+        initial_value: Some(value),
+        ..pin_config.clone()
+    }
+}
+
+fn drive_operations(pin_config: &PinConfig, value: u8) -> Vec<Operation> {
+    let active_low = pin_config
+        .get_pin()
+        .get_active_low()
+        .unwrap_or(pin_config.active_low);
+    export::drive_operations(pin_config.num, value, active_low)
+}
+
+fn poll(pin_config: &PinConfig, edge: Edge, timeout: Option<Duration>) -> Result<Option<u8>> {
+    let num = pin_config.num;
+    let pin = pin_config.get_pin();
+    let _edge = EdgeGuard::new(num, edge)?;
+
+    let mut poller = pin
+        .get_poller()
+        .map_err(|e| Error::pin_operation(num, "create poller", e))?;
+    let deadline = timeout.map(|t| Instant::now() + t);
+    loop {
+        let timeout = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                isize::try_from(remaining.as_millis()).unwrap_or(isize::MAX)
+            }
+            None => -1,
+        };
+        let value = match poller
+            .poll(timeout)
+            .map_err(|e| Error::pin_operation(num, "poll", e))?
+        {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        // a pin left at `both` also reports the edges not asked for
+        let observed = if value == 1 {
+            Edge::RisingEdge
+        } else {
+            Edge::FallingEdge
+        };
+        if edge == Edge::BothEdges || edge == observed {
+            return Ok(Some(value));
+        }
+    }
+}
+
+fn poll_filtered(
+    pin_config: &PinConfig,
+    edge: Edge,
+    timeout: Option<Duration>,
+) -> Result<Option<u8>> {
+    let mut monitor = Monitor::new()?;
+    monitor.add(pin_config, &pin_config.num.to_string())?;
+
+    let deadline = timeout.map(|t| Instant::now() + t);
+    loop {
+        let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        let event = match monitor.next_event(remaining)? {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+        if edge == Edge::BothEdges || edge == event.edge {
+            return Ok(Some(event.value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    const GPIO_CFG: &str = r#"
+[[pins]]
+num = 37
+names = ["status_led"]
+direction = "out"
+
+[[pins]]
+num = 73
+names = ["reset_button"]
+direction = "in"
+
+[[pins]]
+num = 74
+names = ["spare"]
+"#;
+
+    #[test]
+    fn test_level() {
+        assert_eq!(Level::from_str("1").unwrap(), Level::Logical(1));
+        assert_eq!(Level::from_str("inactive").unwrap(), Level::Logical(0));
+        assert_eq!(Level::from_str("high").unwrap(), Level::Physical(1));
+        assert!(Level::from_str("2").is_err());
+
+        assert_eq!(Level::Logical(1).value(true), 1);
+        assert_eq!(Level::Physical(1).value(false), 1);
+        assert_eq!(Level::Physical(1).value(true), 0);
+        assert_eq!(Level::Physical(0).value(true), 1);
+    }
+
+    #[test]
+    fn test_write_operations_check_writable() {
+        let gpio = Gpio::new(GpioConfig::from_str(GPIO_CFG).unwrap());
+        let button = gpio.pin("reset_button").unwrap();
+        match gpio.write_operations(&button, 1, &AccessOptions::default()) {
+            Err(Error::NotWritable { pin: 73, .. }) => {}
+            other => panic!("expected NotWritable, got {:?}", other),
+        }
+
+        let force = AccessOptions {
+            force: true,
+            ..Default::default()
+        };
+        assert!(gpio.write_operations(&button, 1, &force).is_ok());
+        // only an explicit direction = "in" is refused
+        let spare = gpio.pin("spare").unwrap();
+        assert!(gpio
+            .write_operations(&spare, 1, &AccessOptions::default())
+            .is_ok());
+        // ad-hoc pins have no configured direction to honor
+        let adhoc = gpio.pin("88").unwrap();
+        assert!(gpio
+            .write_operations(&adhoc, 1, &AccessOptions::default())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_export_operations_ad_hoc() {
+        let gpio = Gpio::new(GpioConfig::from_str(GPIO_CFG).unwrap());
+        let opts = ExportOptions::default();
+        // ad-hoc pins keep their direction and polarity
+        let adhoc = gpio.pin("999999").unwrap();
+        assert_eq!(
+            gpio.export_operations(&adhoc, &opts).unwrap(),
+            vec![
+                Operation::Export { pin: 999_999 },
+                Operation::WaitReady {
+                    pin: 999_999,
+                    ready: opts.ready
+                },
+            ]
+        );
+        // unless they are written to
+        let target = write_target(&adhoc, 1);
+        assert!(gpio.export_operations(&target, &opts).unwrap().contains(
+            &Operation::SetDirection {
+                pin: 999_999,
+                direction: Direction::High
+            }
+        ));
+    }
+}
@@ -6,8 +6,19 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
+pub mod apply;
+pub mod button;
 pub mod commands;
 pub mod config;
 pub mod error;
 pub mod export;
+pub mod expr;
+pub mod filter;
+pub mod gpio;
+pub mod monitor;
 pub mod options;
+pub mod sequence;
+pub mod state;
+#[cfg(test)]
+mod test_util;
+pub mod udev;
@@ -8,9 +8,11 @@
 
 use clap::{Parser, Subcommand};
 use gpio_utils::commands::*;
-use gpio_utils::config::{self, GpioConfig};
+use gpio_utils::config::GpioConfig;
+use gpio_utils::error::{Error, EXIT_CONFIG};
+use gpio_utils::gpio::Gpio;
 use gpio_utils::options::*;
-use std::process;
+use gpio_utils::udev;
 
 pub const CONFIG_ENV_VAR: &str = "GPIO_UTILS_CONFIG";
 pub const SYMLINK_ROOT_ENV_VAR: &str = "GPIO_UTILS_SYMLINK_ROOT";
@@ -25,6 +27,9 @@ struct Cli {
     /// additional configuration to use (separator ':')
     #[arg(short, long = "config", value_name = "FILE", num_args = 0.., env = CONFIG_ENV_VAR, value_delimiter = ':')]
     configs: Vec<String>,
+    /// output format of read, poll, wait, status, monitor and button
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
     #[command(subcommand)]
     command: Commands,
 }
@@ -35,6 +40,9 @@ enum Commands {
     Read {
         /// The pin name (or number)
         pin: String,
+        /// keep the pin exported if it had to be exported temporarily
+        #[arg(long)]
+        keep: bool,
     },
     /// Wait for an event to happen on a GPIO Input
     Poll {
@@ -46,6 +54,72 @@ enum Commands {
         /// The edge to poll on
         #[arg(short, long)]
         edge: Option<String>,
+        /// report a change only once the level has been stable for this long (ms)
+        #[arg(long, value_name = "MS")]
+        debounce: Option<u64>,
+        /// ignore pulses shorter than this (ms)
+        #[arg(long, value_name = "MS")]
+        min_pulse: Option<u64>,
+        /// keep the pin exported if it had to be exported temporarily
+        #[arg(long)]
+        keep: bool,
+    },
+    /// Wait until a GPIO Input is at a level (returns at once if it already is)
+    Wait {
+        /// The pin name (or number)
+        #[arg(required_unless_present = "expr")]
+        pin: Option<String>,
+        /// The level to wait for (0|1|inactive|active, or low|high for the
+        /// electrical level regardless of active_low)
+        #[arg(required_unless_present = "expr")]
+        level: Option<String>,
+        /// wait until an expression over pins holds instead, e.g.
+        /// "door_closed == 1 && (interlock == 0 || !reset)"
+        #[arg(short = 'x', long, conflicts_with_all = ["pin", "level"])]
+        expr: Option<String>,
+        /// Timeout (in ms) for the wait (-1 to wait forever, default)
+        #[arg(short, long)]
+        timeout: Option<isize>,
+        /// keep the pin exported if it had to be exported temporarily
+        #[arg(long)]
+        keep: bool,
+    },
+    /// Print events on any number of GPIO Inputs until interrupted
+    Monitor {
+        /// The pin names (or numbers); all configured inputs if none are given
+        pins: Vec<String>,
+        /// Exit after this many events
+        #[arg(short, long)]
+        count: Option<u64>,
+        /// report a change only once the level has been stable for this long (ms)
+        #[arg(long, value_name = "MS")]
+        debounce: Option<u64>,
+        /// ignore pulses shorter than this (ms)
+        #[arg(long, value_name = "MS")]
+        min_pulse: Option<u64>,
+    },
+    /// Print (or run the configured commands for) gestures on a button
+    Button {
+        /// The pin name (or number)
+        pin: String,
+        /// Exit after this many gestures
+        #[arg(short, long)]
+        count: Option<u64>,
+        /// report a change only once the level has been stable for this long (ms)
+        #[arg(long, value_name = "MS")]
+        debounce: Option<u64>,
+        /// presses lasting this long are long presses (ms, 0 to disable)
+        #[arg(long, value_name = "MS")]
+        long_press: Option<u64>,
+        /// maximum time between the presses of a double click (ms, 0 to disable)
+        #[arg(long, value_name = "MS")]
+        double_click: Option<u64>,
+        /// print the gestures instead of running the configured commands
+        #[arg(long)]
+        no_exec: bool,
+        /// keep the pin exported if it had to be exported temporarily
+        #[arg(long)]
+        keep: bool,
     },
     /// Write the value of a GPIO Output
     Write {
@@ -53,6 +127,15 @@ enum Commands {
         pin: String,
         /// Value to write to pin (0|1)
         value: u8,
+        /// only print the operations that would be performed
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+        /// write even if the pin is configured as an input or not writable
+        #[arg(short = 'f', long)]
+        force: bool,
+        /// keep the pin exported if it had to be exported temporarily
+        #[arg(long)]
+        keep: bool,
     },
     /// Export a given GPIO
     Export {
@@ -61,12 +144,30 @@ enum Commands {
         /// root directory for export symlinks
         #[arg(short = 'r', long, env = SYMLINK_ROOT_ENV_VAR)]
         symlink_root: Option<String>,
+        /// do not create symlinks (takes precedence over --symlink-root)
+        #[arg(long)]
+        no_symlinks: bool,
+        /// only print the operations that would be performed
+        #[arg(short = 'n', long)]
+        dry_run: bool,
     },
     /// Export all configured GPIOs
     ExportAll {
         /// Export all configured GPIOs
         #[arg(short = 'r', long, env = SYMLINK_ROOT_ENV_VAR)]
         symlink_root: Option<String>,
+        /// do not create symlinks (takes precedence over --symlink-root)
+        #[arg(long)]
+        no_symlinks: bool,
+        /// only print the operations that would be performed
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+        /// on failure, undo the export of all previously exported pins
+        #[arg(long, conflicts_with = "keep_going")]
+        atomic: bool,
+        /// attempt to export all pins and print a summary of the results
+        #[arg(short = 'k', long)]
+        keep_going: bool,
     },
     /// Export all configured GPIOs
     Unexport {
@@ -75,18 +176,96 @@ enum Commands {
         /// root directory for export symlinks
         #[arg(short = 'r', long, env = SYMLINK_ROOT_ENV_VAR)]
         symlink_root: Option<String>,
+        /// only print the operations that would be performed
+        #[arg(short = 'n', long)]
+        dry_run: bool,
     },
     /// Unexport all configured, exported GPIOs
     UnexportAll {
         /// root directory for export symlinks
         #[arg(short = 'r', long, env = SYMLINK_ROOT_ENV_VAR)]
         symlink_root: Option<String>,
+        /// only print the operations that would be performed
+        #[arg(short = 'n', long)]
+        dry_run: bool,
     },
     /// Output status of a GPIO or all GPIOs if no pin is specified
     Status {
         /// The pin name (or number)
         pin: Option<String>,
     },
+    /// Bring exported GPIOs, their settings and symlinks in line with the config
+    Apply {
+        /// root directory for export symlinks
+        #[arg(short = 'r', long, env = SYMLINK_ROOT_ENV_VAR)]
+        symlink_root: Option<String>,
+        /// do not manage symlinks (takes precedence over --symlink-root)
+        #[arg(long)]
+        no_symlinks: bool,
+        /// only print the changes; exit with status 10 if there are any
+        #[arg(long)]
+        plan: bool,
+        /// also unexport pins configured with export = false
+        #[arg(long)]
+        unexport_unmanaged: bool,
+    },
+    /// Run a configured sequence of steps
+    Sequence {
+        /// The sequence name
+        name: String,
+    },
+    /// Drive all exported outputs to their configured safe value
+    SafeState {
+        /// only print the operations that would be performed
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Save, restore or compare the state of the configured GPIOs
+    State {
+        #[command(subcommand)]
+        command: StateCommands,
+    },
+    /// Remove orphaned and dangling symlinks from the symlink root
+    Gc {
+        /// root directory for export symlinks
+        #[arg(short = 'r', long, env = SYMLINK_ROOT_ENV_VAR)]
+        symlink_root: Option<String>,
+        /// only list the symlinks that would be removed
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Generate udev rules enforcing configured GPIO ownership and mode
+    UdevRules {
+        /// Install the rules (to the default udev rules path if none is given)
+        #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = udev::DEFAULT_RULES_PATH)]
+        install: Option<String>,
+        /// Compare the installed rules against the generated ones and exit
+        /// with a non-zero status if they differ
+        #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = udev::DEFAULT_RULES_PATH, conflicts_with = "install")]
+        check: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum StateCommands {
+    /// Save value, direction, active_low and edge of the exported GPIOs
+    Save {
+        /// The snapshot file
+        file: String,
+    },
+    /// Reapply a saved snapshot
+    Restore {
+        /// The snapshot file
+        file: String,
+        /// only print the operations that would be performed
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Compare a saved snapshot with the live state
+    Diff {
+        /// The snapshot file
+        file: String,
+    },
 }
 
 fn main() {
@@ -96,82 +275,245 @@ fn main() {
 
     let gpio_opts = GpioOptions {
         configs: cli.configs.clone(),
+        format: cli.format,
     };
 
     // parse the config
     let cfg = match GpioConfig::load(&gpio_opts.configs[..]) {
         Ok(cfg) => cfg,
-        Err(config::Error::NoConfigFound) => Default::default(),
-        Err(e) => {
-            println!("Error parsing config.  Details follow...");
-            println!("{}", e);
-            process::exit(1);
-        }
+        Err(Error::NoConfigFound) => Default::default(),
+        Err(e) => exit_with_error(
+            gpio_opts.format,
+            EXIT_CONFIG,
+            format_args!("Error parsing config: {}", e),
+        ),
     };
 
-    // TODO: Why are we passing the gpio_options and the config parsed from it to `gpio_read::main`
-    // and the other handlers?
-    match cli.command {
-        Commands::Read { pin } => {
+    let gpio = Gpio::new(cfg);
+    let format = gpio_opts.format;
+    let result = match cli.command {
+        Commands::Read { pin, keep } => {
             let options = GpioReadOptions {
                 gpio_opts,
                 pin: &pin,
+                keep,
             };
-            gpio_read::main(&cfg, &options);
+            gpio_read::main(&gpio, &options)
         }
-        Commands::Poll { pin, timeout, edge } => {
+        Commands::Poll {
+            pin,
+            timeout,
+            edge,
+            debounce,
+            min_pulse,
+            keep,
+        } => {
             let options = GpioPollOptions {
                 gpio_opts,
                 timeout,
                 edge: &edge.unwrap_or_else(|| String::from("both")),
                 pin: &pin,
+                debounce_ms: debounce,
+                min_pulse_ms: min_pulse,
+                keep,
+            };
+            gpio_poll::main(&gpio, &options)
+        }
+        Commands::Wait {
+            pin,
+            level,
+            expr,
+            timeout,
+            keep,
+        } => {
+            let options = GpioWaitOptions {
+                gpio_opts,
+                timeout,
+                pin: pin.as_deref(),
+                level: level.as_deref(),
+                expr: expr.as_deref(),
+                keep,
+            };
+            gpio_wait::main(&gpio, &options)
+        }
+        Commands::Monitor {
+            pins,
+            count,
+            debounce,
+            min_pulse,
+        } => {
+            let options = GpioMonitorOptions {
+                gpio_opts,
+                pins: pins.iter().map(|p| &p[..]).collect(),
+                count,
+                debounce_ms: debounce,
+                min_pulse_ms: min_pulse,
+            };
+            gpio_monitor::main(&gpio, &options)
+        }
+        Commands::Button {
+            pin,
+            count,
+            debounce,
+            long_press,
+            double_click,
+            no_exec,
+            keep,
+        } => {
+            let options = GpioButtonOptions {
+                gpio_opts,
+                pin: &pin,
+                count,
+                debounce_ms: debounce,
+                long_press_ms: long_press,
+                double_click_ms: double_click,
+                no_exec,
+                keep,
             };
-            gpio_poll::main(&cfg, &options);
+            gpio_button::main(&gpio, &options)
         }
-        Commands::Write { pin, value } => {
+        Commands::Write {
+            pin,
+            value,
+            dry_run,
+            force,
+            keep,
+        } => {
             let options = GpioWriteOptions {
                 gpio_opts,
                 pin: &pin,
                 value,
+                dry_run,
+                force,
+                keep,
             };
-            gpio_write::main(&cfg, &options);
+            gpio_write::main(&gpio, &options)
         }
-        Commands::Export { pin, symlink_root } => {
+        Commands::Export {
+            pin,
+            symlink_root,
+            no_symlinks,
+            dry_run,
+        } => {
             let options = GpioExportOptions {
                 gpio_opts,
                 pin: &pin,
                 symlink_root: symlink_root.as_deref(),
+                no_symlinks,
+                dry_run,
             };
-            gpio_export::main(&cfg, &options);
+            gpio_export::main(&gpio, &options)
         }
-        Commands::ExportAll { symlink_root } => {
+        Commands::ExportAll {
+            symlink_root,
+            no_symlinks,
+            dry_run,
+            atomic,
+            keep_going,
+        } => {
             let options = GpioExportAllOptions {
                 gpio_opts,
                 symlink_root: symlink_root.as_deref(),
+                no_symlinks,
+                dry_run,
+                atomic,
+                keep_going,
             };
-            gpio_exportall::main(&cfg, &options);
+            gpio_exportall::main(&gpio, &options)
         }
-        Commands::Unexport { pin, symlink_root } => {
+        Commands::Unexport {
+            pin,
+            symlink_root,
+            dry_run,
+        } => {
             let options = GpioUnexportOptions {
                 gpio_opts,
                 pin: &pin,
                 symlink_root: symlink_root.as_deref(),
+                dry_run,
             };
-            gpio_unexport::main(&cfg, &options);
+            gpio_unexport::main(&gpio, &options)
         }
-        Commands::UnexportAll { symlink_root } => {
+        Commands::UnexportAll {
+            symlink_root,
+            dry_run,
+        } => {
             let options = GpioUnexportAllOptions {
                 gpio_opts,
                 symlink_root: symlink_root.as_deref(),
+                dry_run,
             };
-            gpio_unexportall::main(&cfg, &options);
+            gpio_unexportall::main(&gpio, &options)
         }
         Commands::Status { pin } => {
             let options = GpioStatusOptions {
                 gpio_opts,
                 pin: pin.as_deref(),
             };
-            gpio_status::main(&cfg, &options);
+            gpio_status::main(&gpio, &options)
+        }
+        Commands::Apply {
+            symlink_root,
+            no_symlinks,
+            plan,
+            unexport_unmanaged,
+        } => {
+            let options = GpioApplyOptions {
+                gpio_opts,
+                symlink_root: symlink_root.as_deref(),
+                no_symlinks,
+                plan,
+                unexport_unmanaged,
+            };
+            gpio_apply::main(&gpio, &options)
         }
+        Commands::Sequence { name } => {
+            let options = GpioSequenceOptions {
+                gpio_opts,
+                name: &name,
+            };
+            gpio_sequence::main(&gpio, &options)
+        }
+        Commands::SafeState { dry_run } => {
+            let options = GpioSafeStateOptions { gpio_opts, dry_run };
+            gpio_safe_state::main(&gpio, &options)
+        }
+        Commands::State { command } => {
+            let (action, file) = match command {
+                StateCommands::Save { file } => (GpioStateAction::Save, file),
+                StateCommands::Restore { file, dry_run } => {
+                    (GpioStateAction::Restore { dry_run }, file)
+                }
+                StateCommands::Diff { file } => (GpioStateAction::Diff, file),
+            };
+            let options = GpioStateOptions {
+                gpio_opts,
+                action,
+                file: &file,
+            };
+            gpio_state::main(&gpio, &options)
+        }
+        Commands::Gc {
+            symlink_root,
+            dry_run,
+        } => {
+            let options = GpioGcOptions {
+                gpio_opts,
+                symlink_root: symlink_root.as_deref(),
+                dry_run,
+            };
+            gpio_gc::main(&gpio, &options)
+        }
+        Commands::UdevRules { install, check } => {
+            let options = GpioUdevRulesOptions {
+                gpio_opts,
+                install: install.as_deref(),
+                check: check.as_deref(),
+            };
+            gpio_udev_rules::main(&gpio, &options)
+        }
+    };
+    if let Err(e) = result {
+        exit_with_error(format, e.exit_code(), e);
     }
 }
@@ -0,0 +1,300 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::config::PinConfig;
+use crate::error::*;
+use crate::export::{edge_str, sysfs_path, EdgeGuard};
+use crate::filter::{Change, EdgeFilter, FilterConfig};
+use nix::sys::epoll::{Epoll, EpollCreateFlags, EpollEvent, EpollFlags, EpollTimeout};
+use nix::sys::signal::{SigSet, SigmaskHow, Signal};
+use nix::sys::signalfd::{SfdFlags, SignalFd};
+use nix::time::{clock_gettime, ClockId};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sysfs_gpio::Edge;
+
+/// Maximum number of events retrieved from epoll at once
+const MAX_EVENTS: usize = 16;
+
+/// Epoll data of the signalfd, the pins use their index
+const SIGNAL_TOKEN: u64 = u64::MAX;
+
+/// A change of the value of a monitored pin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub pin: u64,
+    /// The name the pin was added to the monitor with
+    pub name: String,
+    pub value: u8,
+    /// `RisingEdge` if the value became 1, `FallingEdge` if it became 0
+    pub edge: Edge,
+    /// Time since boot (`CLOCK_MONOTONIC`) at which the event was observed
+    pub monotonic: Duration,
+    /// Wall clock time at which the event was observed
+    pub realtime: SystemTime,
+}
+
+struct MonitoredPin {
+    num: u64,
+    name: String,
+    value_file: File,
+    filter: EdgeFilter,
+    /// Restores the edge of the pin when the monitor is dropped
+    _edge: EdgeGuard,
+}
+
+/// Watch the values of any number of pins for changes
+///
+/// All pins are waited on simultaneously with a single epoll instance over
+/// their `value` files, so no edge is missed between events on different
+/// pins.  Pins must be exported; their `edge` is set to `both` until the
+/// monitor is dropped.  Events of
+/// pins with `debounce_ms` or `min_pulse_ms` configured are filtered (see
+/// `EdgeFilter`).
+///
+/// `SIGINT` and `SIGTERM` are blocked while the monitor exists and received
+/// through a signalfd in the same epoll set instead, so that waiting fails
+/// with an `Error::Interrupted` and the edges (and any temporary exports of
+/// the caller) are restored as the stack unwinds.
+pub struct Monitor {
+    epoll: Epoll,
+    pins: Vec<MonitoredPin>,
+    pending: VecDeque<Event>,
+    signals: SignalFd,
+    /// Signal mask to restore when the monitor is dropped
+    old_mask: SigSet,
+}
+
+fn read_value(file: &File) -> Result<u8> {
+    let mut buf = [0u8; 1];
+    file.read_at(&mut buf, 0)?;
+    match buf[0] {
+        b'0' => Ok(0),
+        b'1' => Ok(1),
+        other => Err(Error::Msg(format!(
+            "unexpected value {:?} in value file",
+            other as char
+        ))),
+    }
+}
+
+/// Epoll timeout for `timeout`, rounded up to the next millisecond
+fn epoll_timeout(timeout: Duration) -> EpollTimeout {
+    let timeout = timeout + Duration::from_nanos(999_999);
+    EpollTimeout::try_from(timeout).unwrap_or(EpollTimeout::MAX)
+}
+
+/// Current monotonic (`CLOCK_MONOTONIC`) and wall clock time
+pub fn now() -> Result<(Duration, SystemTime)> {
+    let monotonic = Duration::from(clock_gettime(ClockId::CLOCK_MONOTONIC)?);
+    Ok((monotonic, SystemTime::now()))
+}
+
+impl Monitor {
+    pub fn new() -> Result<Monitor> {
+        let epoll = Epoll::new(EpollCreateFlags::EPOLL_CLOEXEC)?;
+        let mut mask = SigSet::empty();
+        mask.add(Signal::SIGINT);
+        mask.add(Signal::SIGTERM);
+        let old_mask = mask.thread_swap_mask(SigmaskHow::SIG_BLOCK)?;
+        let signals = SignalFd::with_flags(&mask, SfdFlags::SFD_CLOEXEC | SfdFlags::SFD_NONBLOCK)
+            .and_then(|signals| {
+                let event = EpollEvent::new(EpollFlags::EPOLLIN, SIGNAL_TOKEN);
+                epoll.add(&signals, event)?;
+                Ok(signals)
+            });
+        let signals = match signals {
+            Ok(signals) => signals,
+            Err(e) => {
+                let _ = old_mask.thread_set_mask();
+                return Err(e.into());
+            }
+        };
+        Ok(Monitor {
+            epoll,
+            pins: Vec::new(),
+            pending: VecDeque::new(),
+            signals,
+            old_mask,
+        })
+    }
+
+    /// Start watching a pin, reporting its events under `name`
+    pub fn add(&mut self, pin_config: &PinConfig, name: &str) -> Result<()> {
+        let num = pin_config.num;
+        let edge = EdgeGuard::new(num, Edge::BothEdges)?;
+        let value_file = File::open(sysfs_path(num).join("value"))
+            .map_err(|e| Error::pin_operation(num, "open value", e))?;
+        // sysfs reports a value file as ready until it has been read once
+        let value =
+            read_value(&value_file).map_err(|e| Error::pin_operation(num, "read value", e))?;
+        let event = EpollEvent::new(
+            EpollFlags::EPOLLPRI | EpollFlags::EPOLLET,
+            self.pins.len() as u64,
+        );
+        self.epoll.add(&value_file, event)?;
+        self.pins.push(MonitoredPin {
+            num: pin_config.num,
+            name: name.to_string(),
+            value_file,
+            filter: EdgeFilter::new(FilterConfig::from_pin(pin_config), value),
+            _edge: edge,
+        });
+        Ok(())
+    }
+
+    /// Wait for the next event on any of the pins
+    ///
+    /// Returns `None` if no event occurred within `timeout` (if any), and
+    /// fails with an `Error::Interrupted` if `SIGINT` or `SIGTERM` is
+    /// received.
+    pub fn next_event(&mut self, timeout: Option<Duration>) -> Result<Option<Event>> {
+        let deadline = match timeout {
+            Some(timeout) => Some(now()?.0 + timeout),
+            None => None,
+        };
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+
+            let (monotonic, realtime) = now()?;
+            for pin in &mut self.pins {
+                if let Some(change) = pin.filter.poll(monotonic) {
+                    let event = pin.event(change, monotonic, realtime);
+                    self.pending.push_back(event);
+                }
+            }
+            if !self.pending.is_empty() {
+                continue;
+            }
+            if deadline.is_some_and(|deadline| monotonic >= deadline) {
+                return Ok(None);
+            }
+
+            // wake up when the user's timeout expires or a pending change
+            // of a filtered pin may be confirmed, whichever comes first
+            let wake = self
+                .pins
+                .iter()
+                .filter_map(|pin| pin.filter.deadline())
+                .chain(deadline)
+                .min();
+            let timeout = match wake {
+                Some(wake) => epoll_timeout(wake.saturating_sub(monotonic)),
+                None => EpollTimeout::NONE,
+            };
+            let mut events = [EpollEvent::empty(); MAX_EVENTS];
+            let count = self.epoll.wait(&mut events, timeout)?;
+            let (monotonic, realtime) = now()?;
+            for event in &events[..count] {
+                if event.data() == SIGNAL_TOKEN {
+                    if let Some(info) = self.signals.read_signal()? {
+                        let signal = Signal::try_from(info.ssi_signo as i32)?;
+                        return Err(Error::Interrupted { signal });
+                    }
+                    continue;
+                }
+                let pin = &mut self.pins[event.data() as usize];
+                let value = read_value(&pin.value_file)
+                    .map_err(|e| Error::pin_operation(pin.num, "read value", e))?;
+                if let Some(change) = pin.filter.edge(value, monotonic) {
+                    let event = pin.event(change, monotonic, realtime);
+                    self.pending.push_back(event);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        // restore the edges before a signal still pending can be delivered
+        self.pins.clear();
+        let _ = self.old_mask.thread_set_mask();
+    }
+}
+
+impl MonitoredPin {
+    /// Event for a change let through by the filter
+    ///
+    /// The wall clock time of the change is derived from the clocks read at
+    /// `monotonic` and `realtime`, as the filter may report it in the past.
+    fn event(&self, change: Change, monotonic: Duration, realtime: SystemTime) -> Event {
+        Event {
+            pin: self.num,
+            name: self.name.clone(),
+            value: change.value,
+            edge: if change.value == 1 {
+                Edge::RisingEdge
+            } else {
+                Edge::FallingEdge
+            },
+            monotonic: change.at,
+            realtime: realtime - monotonic.saturating_sub(change.at),
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    /// Format the event as `<realtime> <monotonic> <name> <value> <edge>`
+    ///
+    /// Both timestamps are in seconds, with microsecond resolution.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let realtime = self
+            .realtime
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO);
+        write!(
+            f,
+            "{}.{:06} {}.{:06} {} {} {}",
+            realtime.as_secs(),
+            realtime.subsec_micros(),
+            self.monotonic.as_secs(),
+            self.monotonic.subsec_micros(),
+            self.name,
+            self.value,
+            edge_str(self.edge)
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_event_display() {
+        let event = Event {
+            pin: 73,
+            name: String::from("reset_button"),
+            value: 1,
+            edge: Edge::RisingEdge,
+            monotonic: Duration::from_micros(12_345_678_901),
+            realtime: UNIX_EPOCH + Duration::from_micros(1_760_000_000_000_042),
+        };
+        assert_eq!(
+            event.to_string(),
+            "1760000000.000042 12345.678901 reset_button 1 rising"
+        );
+    }
+
+    #[test]
+    fn test_interrupted() {
+        let mut monitor = Monitor::new().unwrap();
+        // blocked while the monitor exists, so this only makes it pending
+        nix::sys::signal::raise(Signal::SIGINT).unwrap();
+        match monitor.next_event(Some(Duration::from_secs(1))) {
+            Err(Error::Interrupted { signal }) => assert_eq!(signal, Signal::SIGINT),
+            other => panic!("expected an interruption, got {:?}", other),
+        }
+    }
+}
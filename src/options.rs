@@ -6,15 +6,28 @@
 // option.  This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use clap::ValueEnum;
+
+/// Format of the output of commands reporting values, state or events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    /// One JSON object per line on stdout, errors as JSON objects on stderr
+    Json,
+}
+
 #[derive(Debug)]
 pub struct GpioOptions {
     pub configs: Vec<String>,
+    pub format: OutputFormat,
 }
 
 #[derive(Debug)]
 pub struct GpioReadOptions<'a> {
     pub gpio_opts: GpioOptions,
     pub pin: &'a str,
+    pub keep: bool,
 }
 
 #[derive(Debug)]
@@ -22,6 +35,9 @@ pub struct GpioWriteOptions<'a> {
     pub gpio_opts: GpioOptions,
     pub pin: &'a str,
     pub value: u8,
+    pub dry_run: bool,
+    pub force: bool,
+    pub keep: bool,
 }
 
 #[derive(Debug)]
@@ -30,19 +46,38 @@ pub struct GpioPollOptions<'a> {
     pub timeout: Option<isize>,
     pub edge: &'a str,
     pub pin: &'a str,
+    pub debounce_ms: Option<u64>,
+    pub min_pulse_ms: Option<u64>,
+    pub keep: bool,
+}
+
+#[derive(Debug)]
+pub struct GpioWaitOptions<'a> {
+    pub gpio_opts: GpioOptions,
+    pub timeout: Option<isize>,
+    pub pin: Option<&'a str>,
+    pub level: Option<&'a str>,
+    pub expr: Option<&'a str>,
+    pub keep: bool,
 }
 
 #[derive(Debug)]
 pub struct GpioExportOptions<'a> {
     pub gpio_opts: GpioOptions,
     pub symlink_root: Option<&'a str>,
+    pub no_symlinks: bool,
     pub pin: &'a str,
+    pub dry_run: bool,
 }
 
 #[derive(Debug)]
 pub struct GpioExportAllOptions<'a> {
     pub gpio_opts: GpioOptions,
     pub symlink_root: Option<&'a str>,
+    pub no_symlinks: bool,
+    pub dry_run: bool,
+    pub atomic: bool,
+    pub keep_going: bool,
 }
 
 #[derive(Debug)]
@@ -50,12 +85,14 @@ pub struct GpioUnexportOptions<'a> {
     pub gpio_opts: GpioOptions,
     pub symlink_root: Option<&'a str>,
     pub pin: &'a str,
+    pub dry_run: bool,
 }
 
 #[derive(Debug)]
 pub struct GpioUnexportAllOptions<'a> {
     pub gpio_opts: GpioOptions,
     pub symlink_root: Option<&'a str>,
+    pub dry_run: bool,
 }
 
 #[derive(Debug)]
@@ -63,3 +100,73 @@ pub struct GpioStatusOptions<'a> {
     pub gpio_opts: GpioOptions,
     pub pin: Option<&'a str>,
 }
+
+#[derive(Debug)]
+pub struct GpioUdevRulesOptions<'a> {
+    pub gpio_opts: GpioOptions,
+    pub install: Option<&'a str>,
+    pub check: Option<&'a str>,
+}
+
+#[derive(Debug)]
+pub struct GpioGcOptions<'a> {
+    pub gpio_opts: GpioOptions,
+    pub symlink_root: Option<&'a str>,
+    pub dry_run: bool,
+}
+
+#[derive(Debug)]
+pub struct GpioApplyOptions<'a> {
+    pub gpio_opts: GpioOptions,
+    pub symlink_root: Option<&'a str>,
+    pub no_symlinks: bool,
+    pub plan: bool,
+    pub unexport_unmanaged: bool,
+}
+
+#[derive(Debug)]
+pub struct GpioSequenceOptions<'a> {
+    pub gpio_opts: GpioOptions,
+    pub name: &'a str,
+}
+
+#[derive(Debug)]
+pub struct GpioSafeStateOptions {
+    pub gpio_opts: GpioOptions,
+    pub dry_run: bool,
+}
+
+#[derive(Debug)]
+pub enum GpioStateAction {
+    Save,
+    Restore { dry_run: bool },
+    Diff,
+}
+
+#[derive(Debug)]
+pub struct GpioStateOptions<'a> {
+    pub gpio_opts: GpioOptions,
+    pub action: GpioStateAction,
+    pub file: &'a str,
+}
+
+#[derive(Debug)]
+pub struct GpioMonitorOptions<'a> {
+    pub gpio_opts: GpioOptions,
+    pub pins: Vec<&'a str>,
+    pub count: Option<u64>,
+    pub debounce_ms: Option<u64>,
+    pub min_pulse_ms: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct GpioButtonOptions<'a> {
+    pub gpio_opts: GpioOptions,
+    pub pin: &'a str,
+    pub count: Option<u64>,
+    pub debounce_ms: Option<u64>,
+    pub long_press_ms: Option<u64>,
+    pub double_click_ms: Option<u64>,
+    pub no_exec: bool,
+    pub keep: bool,
+}
@@ -0,0 +1,92 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::config::{GpioConfig, PinConfig, SequenceStep};
+use crate::error::*;
+use crate::export::{EdgeGuard, Operation};
+use std::thread;
+use std::time::{Duration, Instant};
+use sysfs_gpio::Edge;
+
+fn find_pin<'a>(config: &'a GpioConfig, name: &str) -> Result<&'a PinConfig> {
+    config.get_pin(name).ok_or_else(|| Error::UnknownPin {
+        name: name.to_string(),
+    })
+}
+
+/// Run a single step of a sequence
+///
+/// Pins referenced by the step must be exported.
+pub fn run_step(config: &GpioConfig, step: &SequenceStep) -> Result<()> {
+    match *step {
+        SequenceStep::Set { ref set, value } => {
+            let pin_config = find_pin(config, set)?;
+            Operation::SetValue {
+                pin: pin_config.num,
+                value,
+            }
+            .run()
+        }
+        SequenceStep::WaitFor {
+            ref wait_for,
+            value,
+            timeout_ms,
+        } => wait_for_value(
+            find_pin(config, wait_for)?,
+            value,
+            timeout_ms.map(Duration::from_millis),
+        ),
+        SequenceStep::Wait { wait_ms } => {
+            thread::sleep(Duration::from_millis(wait_ms));
+            Ok(())
+        }
+    }
+}
+
+/// Block until the pin has the provided value
+///
+/// Returns immediately if the pin already has the value.  Otherwise, edges
+/// on the pin are waited for until it does or until `timeout` (if any) has
+/// elapsed, in which case an `Error::Timeout` is returned.  The edge of the
+/// pin is restored afterwards (see `EdgeGuard`).
+pub fn wait_for_value(pin_config: &PinConfig, value: u8, timeout: Option<Duration>) -> Result<()> {
+    let num = pin_config.num;
+    let pin = pin_config.get_pin();
+    let edge = if value == 1 {
+        Edge::RisingEdge
+    } else {
+        Edge::FallingEdge
+    };
+    let _edge = EdgeGuard::new(num, edge)?;
+    let mut poller = pin
+        .get_poller()
+        .map_err(|e| Error::pin_operation(num, "poll", e))?;
+    let deadline = timeout.map(|t| Instant::now() + t);
+    loop {
+        let current = pin
+            .get_value()
+            .map_err(|e| Error::pin_operation(num, "read value", e))?;
+        if current == value {
+            return Ok(());
+        }
+        let timeout_ms = match deadline {
+            None => -1,
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Err(Error::Timeout { pin: num, value });
+                }
+                // round up so that we never wake up before the deadline
+                (remaining.as_micros() as isize + 999) / 1000
+            }
+        };
+        poller
+            .poll(timeout_ms)
+            .map_err(|e| Error::pin_operation(num, "poll", e))?;
+    }
+}
@@ -0,0 +1,474 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::config::{DirectionDef, EdgeDef, GpioConfig};
+use crate::error::*;
+use crate::export::{self, direction_str, edge_str, Operation, ReadyWait};
+use serde_derive::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+use sysfs_gpio::{Direction, Edge};
+
+/// Live attributes of an exported pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Attributes {
+    #[serde(with = "DirectionDef")]
+    pub direction: Direction,
+    pub active_low: bool,
+    #[serde(with = "EdgeDef")]
+    pub edge: Edge,
+    pub value: u8,
+}
+
+/// Live state of a single pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawPinSnapshot")]
+pub struct PinSnapshot {
+    pub num: u64,
+    /// Attributes of the pin, `None` if it is not exported
+    pub attributes: Option<Attributes>,
+}
+
+/// A pin as stored in a snapshot, with `exported = false` (and no
+/// attributes) for pins which are not exported
+#[derive(Deserialize)]
+struct RawPinSnapshot {
+    num: u64,
+    #[serde(default = "exported_default")]
+    exported: bool,
+    #[serde(flatten)]
+    attributes: Option<Attributes>,
+}
+
+fn exported_default() -> bool {
+    true
+}
+
+impl TryFrom<RawPinSnapshot> for PinSnapshot {
+    type Error = String;
+
+    fn try_from(raw: RawPinSnapshot) -> std::result::Result<PinSnapshot, String> {
+        match (raw.exported, raw.attributes) {
+            (true, None) => Err(format!("gpio{}: missing attributes", raw.num)),
+            (false, Some(_)) => Err(format!(
+                "gpio{}: attributes given although it is not exported",
+                raw.num
+            )),
+            (_, attributes) => Ok(PinSnapshot {
+                num: raw.num,
+                attributes,
+            }),
+        }
+    }
+}
+
+/// Live state of the configured pins
+///
+/// Snapshots are stored as TOML, with one `[[pins]]` table per pin; pins
+/// which are not exported are recorded with `exported = false`.  The
+/// `Display` implementation produces this format and `FromStr` parses it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Snapshot {
+    #[serde(default)]
+    pub pins: Vec<PinSnapshot>,
+}
+
+/// Reason a snapshot cannot be restored with the current configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The pin is not in the configuration
+    UnknownPin { pin: u64 },
+    /// The pin is configured as an input but was an output or vice versa
+    DirectionChanged {
+        pin: u64,
+        snapshot: Direction,
+        config: Direction,
+    },
+}
+
+/// Difference between a snapshot and the live state of a pin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// The pin is exported in the snapshot but is not exported any more
+    NotExported { pin: u64 },
+    /// The pin is not exported in the snapshot but is exported now
+    Exported { pin: u64 },
+    /// The pin is exported but is not in the snapshot
+    NotInSnapshot { pin: u64 },
+    /// An attribute differs between the snapshot and the live state
+    Attribute {
+        pin: u64,
+        attribute: &'static str,
+        snapshot: String,
+        live: String,
+    },
+}
+
+fn is_output(direction: Direction) -> bool {
+    direction != Direction::In
+}
+
+impl PinSnapshot {
+    /// Read the state of a pin
+    pub fn capture(num: u64) -> Result<PinSnapshot> {
+        let pin = sysfs_gpio::Pin::new(num);
+        if !pin.is_exported() {
+            return Ok(PinSnapshot {
+                num,
+                attributes: None,
+            });
+        }
+        let attributes = Attributes {
+            direction: pin.get_direction()?,
+            active_low: pin.get_active_low()?,
+            edge: pin.get_edge()?,
+            value: pin.get_value()?,
+        };
+        Ok(PinSnapshot {
+            num,
+            attributes: Some(attributes),
+        })
+    }
+}
+
+impl Snapshot {
+    /// Capture the state of every configured pin
+    pub fn capture(config: &GpioConfig) -> Result<Snapshot> {
+        let mut pins = Vec::new();
+        for pin_config in config.get_pins() {
+            pins.push(PinSnapshot::capture(pin_config.num)?);
+        }
+        Ok(Snapshot { pins })
+    }
+
+    /// Check that the snapshot may be restored with the provided config
+    pub fn validate(&self, config: &GpioConfig) -> Vec<Mismatch> {
+        let mut mismatches = Vec::new();
+        for pin in &self.pins {
+            let pin_config = config.get_pins().iter().find(|p| p.num == pin.num);
+            match (pin_config, pin.attributes) {
+                (None, _) => mismatches.push(Mismatch::UnknownPin { pin: pin.num }),
+                (Some(pin_config), Some(attrs))
+                    if is_output(attrs.direction) != is_output(pin_config.get_direction()) =>
+                {
+                    mismatches.push(Mismatch::DirectionChanged {
+                        pin: pin.num,
+                        snapshot: attrs.direction,
+                        config: pin_config.get_direction(),
+                    })
+                }
+                (Some(_), _) => {}
+            }
+        }
+        mismatches
+    }
+
+    /// Describe the operations reapplying the snapshot
+    ///
+    /// Pins which are not exported are exported first, and pins which were
+    /// not exported in the snapshot are unexported.  The polarity is
+    /// restored before the value, as the value is relative to it.
+    pub fn restore_operations(&self, ready: &ReadyWait) -> Vec<Operation> {
+        let mut operations = Vec::new();
+        for pin in &self.pins {
+            let exported = sysfs_gpio::Pin::new(pin.num).is_exported();
+            let attrs = match pin.attributes {
+                Some(attrs) => attrs,
+                None => {
+                    if exported {
+                        operations.push(Operation::Unexport { pin: pin.num });
+                    }
+                    continue;
+                }
+            };
+            if !exported {
+                operations.push(Operation::Export { pin: pin.num });
+                operations.push(Operation::WaitReady {
+                    pin: pin.num,
+                    ready: *ready,
+                });
+            }
+            operations.push(Operation::SetActiveLow {
+                pin: pin.num,
+                active_low: attrs.active_low,
+            });
+            if is_output(attrs.direction) {
+                operations.extend(export::drive_operations(
+                    pin.num,
+                    attrs.value,
+                    attrs.active_low,
+                ));
+            } else {
+                operations.push(Operation::SetDirection {
+                    pin: pin.num,
+                    direction: attrs.direction,
+                });
+            }
+            operations.push(Operation::SetEdge {
+                pin: pin.num,
+                edge: attrs.edge,
+            });
+        }
+        operations
+    }
+
+    /// Reapply the snapshot
+    pub fn restore(&self, ready: &ReadyWait) -> Result<()> {
+        export::run(&self.restore_operations(ready))
+    }
+
+    /// Compare the snapshot against `live` (e.g. a freshly captured snapshot)
+    pub fn diff(&self, live: &Snapshot) -> Vec<Difference> {
+        let mut differences = Vec::new();
+        for pin in &self.pins {
+            let live_attrs = live
+                .pins
+                .iter()
+                .find(|p| p.num == pin.num)
+                .and_then(|p| p.attributes);
+            let (attrs, live_attrs) = match (pin.attributes, live_attrs) {
+                (Some(attrs), Some(live_attrs)) => (attrs, live_attrs),
+                (Some(_), None) => {
+                    differences.push(Difference::NotExported { pin: pin.num });
+                    continue;
+                }
+                (None, Some(_)) => {
+                    differences.push(Difference::Exported { pin: pin.num });
+                    continue;
+                }
+                (None, None) => continue,
+            };
+            let mut attribute = |attribute: &'static str, snapshot: String, live: String| {
+                if snapshot != live {
+                    differences.push(Difference::Attribute {
+                        pin: pin.num,
+                        attribute,
+                        snapshot,
+                        live,
+                    });
+                }
+            };
+            attribute(
+                "direction",
+                direction_str(attrs.direction).to_string(),
+                direction_str(live_attrs.direction).to_string(),
+            );
+            attribute(
+                "active_low",
+                attrs.active_low.to_string(),
+                live_attrs.active_low.to_string(),
+            );
+            attribute(
+                "edge",
+                edge_str(attrs.edge).to_string(),
+                edge_str(live_attrs.edge).to_string(),
+            );
+            attribute(
+                "value",
+                attrs.value.to_string(),
+                live_attrs.value.to_string(),
+            );
+        }
+        for live_pin in &live.pins {
+            let known = self.pins.iter().any(|p| p.num == live_pin.num);
+            if live_pin.attributes.is_some() && !known {
+                differences.push(Difference::NotInSnapshot { pin: live_pin.num });
+            }
+        }
+        differences
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Snapshot> {
+        toml::from_str(s).map_err(|e| Error::InvalidSnapshot {
+            reason: e.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# Generated by gpio-utils (`gpio state save`)")?;
+        for pin in &self.pins {
+            writeln!(f)?;
+            writeln!(f, "[[pins]]")?;
+            writeln!(f, "num = {}", pin.num)?;
+            let attrs = match pin.attributes {
+                Some(attrs) => attrs,
+                None => {
+                    writeln!(f, "exported = false")?;
+                    continue;
+                }
+            };
+            writeln!(f, "direction = \"{}\"", direction_str(attrs.direction))?;
+            writeln!(f, "active_low = {}", attrs.active_low)?;
+            writeln!(f, "edge = \"{}\"", edge_str(attrs.edge))?;
+            writeln!(f, "value = {}", attrs.value)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Mismatch::UnknownPin { pin } => write!(f, "gpio{}: not in the configuration", pin),
+            Mismatch::DirectionChanged {
+                pin,
+                snapshot,
+                config,
+            } => write!(
+                f,
+                "gpio{}: direction is '{}' in the snapshot but '{}' in the configuration",
+                pin,
+                direction_str(snapshot),
+                direction_str(config)
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Difference::NotExported { pin } => write!(f, "- gpio{}: not exported", pin),
+            Difference::Exported { pin } => write!(f, "+ gpio{}: exported", pin),
+            Difference::NotInSnapshot { pin } => write!(f, "+ gpio{}: not in snapshot", pin),
+            Difference::Attribute {
+                pin,
+                attribute,
+                ref snapshot,
+                ref live,
+            } => write!(f, "~ gpio{}: {} {} -> {}", pin, attribute, snapshot, live),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const STATE_CFG: &str = r#"
+[[pins]]
+num = 37
+names = ["status_led"]
+direction = "out"
+
+[[pins]]
+num = 73
+names = ["reset_button"]
+
+[[pins]]
+num = 41
+names = ["spare"]
+"#;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            pins: vec![
+                PinSnapshot {
+                    num: 37,
+                    attributes: Some(Attributes {
+                        direction: Direction::Out,
+                        active_low: false,
+                        edge: Edge::NoInterrupt,
+                        value: 1,
+                    }),
+                },
+                PinSnapshot {
+                    num: 73,
+                    attributes: Some(Attributes {
+                        direction: Direction::In,
+                        active_low: true,
+                        edge: Edge::BothEdges,
+                        value: 0,
+                    }),
+                },
+                PinSnapshot {
+                    num: 41,
+                    attributes: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let snapshot = snapshot();
+        let saved = snapshot.to_string();
+        assert!(saved.contains("num = 41\nexported = false\n"));
+        assert_eq!(Snapshot::from_str(&saved).unwrap(), snapshot);
+
+        for pin in [
+            "num = 41\nexported = false\ndirection = \"in\"\nactive_low = false\nedge = \"none\"\nvalue = 0\n",
+            "num = 41\nexported = true\n",
+            "num = 41\ndirection = \"in\"\n",
+        ] {
+            match Snapshot::from_str(&format!("[[pins]]\n{}", pin)) {
+                Err(Error::InvalidSnapshot { .. }) => (),
+                r => panic!("Expected InvalidSnapshot Error for {:?}, got {:?}", pin, r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = GpioConfig::from_str(STATE_CFG).unwrap();
+        let mut snapshot = snapshot();
+        assert_eq!(snapshot.validate(&config), vec![]);
+
+        snapshot.pins[1].attributes.as_mut().unwrap().direction = Direction::Out;
+        snapshot.pins.push(PinSnapshot {
+            num: 88,
+            ..snapshot.pins[0]
+        });
+        assert_eq!(
+            snapshot.validate(&config),
+            vec![
+                Mismatch::DirectionChanged {
+                    pin: 73,
+                    snapshot: Direction::Out,
+                    config: Direction::In,
+                },
+                Mismatch::UnknownPin { pin: 88 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let saved = snapshot();
+        assert_eq!(saved.diff(&saved), vec![]);
+
+        let mut live = snapshot();
+        live.pins[0].attributes.as_mut().unwrap().value = 0;
+        live.pins[1].attributes = None;
+        live.pins[2].attributes = saved.pins[1].attributes;
+        live.pins.push(PinSnapshot {
+            num: 88,
+            ..saved.pins[0]
+        });
+        assert_eq!(
+            saved.diff(&live),
+            vec![
+                Difference::Attribute {
+                    pin: 37,
+                    attribute: "value",
+                    snapshot: String::from("1"),
+                    live: String::from("0"),
+                },
+                Difference::NotExported { pin: 73 },
+                Difference::Exported { pin: 41 },
+                Difference::NotInSnapshot { pin: 88 },
+            ]
+        );
+    }
+}
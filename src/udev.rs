@@ -0,0 +1,155 @@
+// Copyright (c) 2026, The gpio-utils Authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::config::{GpioConfig, PinConfig};
+use std::fmt::Write;
+
+/// Default location for the generated rules file
+pub const DEFAULT_RULES_PATH: &str = "/etc/udev/rules.d/60-gpio-utils.rules";
+
+/// Attributes of an exported GPIO whose ownership/mode is managed
+const PIN_ATTRIBUTES: &[&str] = &["active_low", "direction", "edge", "value"];
+
+/// Generate udev rules enforcing the ownership and mode of configured pins
+///
+/// `export::export` applies `user`, `group` and `mode` only once, right after
+/// the pin has been exported.  The rules generated here have udev apply the
+/// same settings every time the kernel exports the pin, regardless of who
+/// requested the export.  Pins without any of these settings are skipped.
+pub fn generate_rules(config: &GpioConfig) -> String {
+    let mut rules = String::new();
+    rules.push_str("# Generated by gpio-utils (`gpio udev-rules`).  Do not edit.\n");
+    for pin_config in config.get_pins() {
+        if let Some(rule) = pin_rule(pin_config) {
+            rules.push('\n');
+            if !pin_config.names.is_empty() {
+                let names: Vec<&str> = pin_config.names.iter().map(|n| &n[..]).collect();
+                let _ = writeln!(rules, "# {}", names.join(", "));
+            }
+            rules.push_str(&rule);
+            rules.push('\n');
+        }
+    }
+    rules
+}
+
+/// Build the rule for a single pin (if it has ownership or mode settings)
+fn pin_rule(pin_config: &PinConfig) -> Option<String> {
+    let owner = match (pin_config.user.as_ref(), pin_config.group.as_ref()) {
+        (Some(user), Some(group)) => Some(format!("{}:{}", user, group)),
+        (Some(user), None) => Some(user.clone()),
+        (None, Some(group)) => Some(format!(":{}", group)),
+        (None, None) => None,
+    };
+    if owner.is_none() && pin_config.mode.is_none() {
+        return None;
+    }
+
+    let files: Vec<String> = PIN_ATTRIBUTES
+        .iter()
+        .map(|attr| format!("/sys%p/{}", attr))
+        .collect();
+    let files = files.join(" ");
+
+    let mut commands = Vec::new();
+    if let Some(owner) = owner {
+        commands.push(format!("chown {} {}", owner, files));
+    }
+    if let Some(mode) = pin_config.mode {
+        commands.push(format!("chmod {:o} {}", mode, files));
+    }
+
+    Some(format!(
+        "SUBSYSTEM==\"gpio\", KERNEL==\"gpio{}\", ACTION==\"add\", \
+         RUN+=\"/bin/sh -c '{}'\"",
+        pin_config.num,
+        commands.join(" && ")
+    ))
+}
+
+/// Produce a line-based diff between the installed and generated rules
+///
+/// Returns `None` if both are identical.  Otherwise, lines only present in
+/// `installed` are prefixed with `-`, lines only present in `generated`
+/// with `+` and common lines with a space.
+pub fn diff(installed: &str, generated: &str) -> Option<String> {
+    if installed == generated {
+        return None;
+    }
+
+    let old: Vec<&str> = installed.lines().collect();
+    let new: Vec<&str> = generated.lines().collect();
+
+    // longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            let _ = writeln!(out, " {}", old[i]);
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            let _ = writeln!(out, "+{}", new[j]);
+            j += 1;
+        } else {
+            let _ = writeln!(out, "-{}", old[i]);
+            i += 1;
+        }
+    }
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    const OWNED_CFG: &str = r#"
+[[pins]]
+num = 73
+names = ["reset_button"]
+user = "root"
+group = "gpio"
+mode = 0o664
+
+[[pins]]
+num = 37
+names = ["status_led"]
+"#;
+
+    #[test]
+    fn test_generate_rules() {
+        let config = GpioConfig::from_str(OWNED_CFG).unwrap();
+        let rules = generate_rules(&config);
+        assert!(rules.contains("# reset_button\n"));
+        assert!(rules.contains(
+            "SUBSYSTEM==\"gpio\", KERNEL==\"gpio73\", ACTION==\"add\", RUN+=\"/bin/sh -c '\
+             chown root:gpio /sys%p/active_low /sys%p/direction /sys%p/edge /sys%p/value && \
+             chmod 664 /sys%p/active_low /sys%p/direction /sys%p/edge /sys%p/value'\"\n"
+        ));
+        assert!(!rules.contains("gpio37"));
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("a\nb\n", "a\nb\n"), None);
+        assert_eq!(diff("a\nb\nc\n", "a\nc\nd\n").unwrap(), " a\n-b\n c\n+d\n");
+    }
+}